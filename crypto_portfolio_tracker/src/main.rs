@@ -1,6 +1,16 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::io::{self, Write};
 
+#[path = "../../shared/parse.rs"]
+#[allow(dead_code)]
+mod parse;
+use parse::{parse_finite_f64, parse_positive_f64, parse_positive_u64};
+#[path = "../../shared/stats.rs"]
+#[allow(dead_code)]
+mod stats;
+use stats::summarize;
+
 // Enum for different cryptocurrency types
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum CryptoCoin {
@@ -18,27 +28,142 @@ enum MenuChoice{
     ViewPortfolio,
     AddCoin,
     ShowPrices,
+    SellCoin,
+    ImportCsv,
+    WhatIf,
+    DisplayPrecision,
+    Opportunities,
+    RemoveCoin,
+    SetAlert,
+    UpdatePrice,
+    MultiCurrency,
+    ResetPrices,
+    ExportJson,
+    ImportJson,
+    DiffSnapshot,
+    BiggestMovers,
+    MarketCaps,
     Exit,
     Invalid(String),
 }
 
+// How displayed totals are rounded to cents
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RoundingMode {
+    HalfUp,
+    HalfEven,
+    Truncate,
+}
+
+impl RoundingMode {
+    fn from_string(input: &str) -> Option<RoundingMode> {
+        match input.trim().to_lowercase().as_str() {
+            "up" | "half-up" => Some(RoundingMode::HalfUp),
+            "even" | "half-even" | "bankers" => Some(RoundingMode::HalfEven),
+            "truncate" | "trunc" => Some(RoundingMode::Truncate),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 enum PortfolioOperation {
-    Replace(f64),
+    Replace,
     Add(f64),
 }
 
+// Every accepted spelling (lowercase) for each coin - add a row to accept a new alias
+const COIN_ALIASES: &[(&str, CryptoCoin)] = &[
+    ("bitcoin", CryptoCoin::Bitcoin),
+    ("btc", CryptoCoin::Bitcoin),
+    ("xbt", CryptoCoin::Bitcoin),
+    ("ethereum", CryptoCoin::Ethereum),
+    ("eth", CryptoCoin::Ethereum),
+    ("ether", CryptoCoin::Ethereum),
+    ("solana", CryptoCoin::Solana),
+    ("sol", CryptoCoin::Solana),
+    ("cardano", CryptoCoin::Cardano),
+    ("ada", CryptoCoin::Cardano),
+    ("polkadot", CryptoCoin::Polkadot),
+    ("dot", CryptoCoin::Polkadot),
+    ("aptos", CryptoCoin::Aptos),
+    ("apt", CryptoCoin::Aptos),
+];
+
 //Implementation block for CryptoCoin enum - adding methods to enums
 impl CryptoCoin{
+    // Case-insensitive lookup in COIN_ALIASES, ignoring surrounding whitespace
     fn from_string(input: &str) -> Option<CryptoCoin>{
-        match input.to_lowercase().as_str(){
-            "bitcoin" | "btc" => Some(CryptoCoin::Bitcoin),
-            "ethereum" | "eth" => Some(CryptoCoin::Ethereum),
-            "solana" | "sol" => Some(CryptoCoin::Solana),
-            "cardano" | "ada" => Some(CryptoCoin::Cardano),
-            "polkadot" | "dot" => Some(CryptoCoin::Polkadot),
-            "aptos" | "apt" => Some(CryptoCoin::Aptos),
-            _ => None,
+        let input = input.trim().to_lowercase();
+        COIN_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == input)
+            .map(|(_, coin)| coin.clone())
+    }
+
+    // All spellings from_string accepts for this coin
+    fn aliases(&self) -> Vec<&'static str> {
+        COIN_ALIASES
+            .iter()
+            .filter(|(_, coin)| coin == self)
+            .map(|(alias, _)| *alias)
+            .collect()
+    }
+
+    // Every coin, in declaration order
+    fn all() -> Vec<CryptoCoin> {
+        let coins = vec![
+            CryptoCoin::Bitcoin,
+            CryptoCoin::Ethereum,
+            CryptoCoin::Solana,
+            CryptoCoin::Cardano,
+            CryptoCoin::Polkadot,
+            CryptoCoin::Aptos,
+        ];
+        // Stops compiling when a variant is added, as a reminder to list it above
+        match CryptoCoin::Bitcoin {
+            CryptoCoin::Bitcoin
+            | CryptoCoin::Ethereum
+            | CryptoCoin::Solana
+            | CryptoCoin::Cardano
+            | CryptoCoin::Polkadot
+            | CryptoCoin::Aptos => coins,
+        }
+    }
+
+    // Starting price used by a new tracker - real world example prices
+    fn default_price(&self) -> f64 {
+        match self {
+            CryptoCoin::Bitcoin => 45000.0,
+            CryptoCoin::Ethereum => 2000.0,
+            CryptoCoin::Solana => 157.0,
+            CryptoCoin::Cardano => 0.45,
+            CryptoCoin::Polkadot => 10.01,
+            CryptoCoin::Aptos => 4.8,
+        }
+    }
+
+    // Example 24h price change in percent used by a new tracker
+    fn default_change_24h(&self) -> f64 {
+        match self {
+            CryptoCoin::Bitcoin => 1.8,
+            CryptoCoin::Ethereum => -2.4,
+            CryptoCoin::Solana => 6.3,
+            CryptoCoin::Cardano => -0.7,
+            CryptoCoin::Polkadot => -4.1,
+            CryptoCoin::Aptos => 3.2,
+        }
+    }
+
+    // Rough circulating supply in coins, used for the market-cap ranking
+    fn default_supply(&self) -> f64 {
+        match self {
+            CryptoCoin::Bitcoin => 19_700_000.0,
+            CryptoCoin::Ethereum => 120_000_000.0,
+            CryptoCoin::Solana => 470_000_000.0,
+            CryptoCoin::Cardano => 35_500_000_000.0,
+            CryptoCoin::Polkadot => 1_450_000_000.0,
+            CryptoCoin::Aptos => 450_000_000.0,
         }
     }
 
@@ -72,33 +197,121 @@ impl MenuChoice{
             "1" => MenuChoice::ViewPortfolio,
             "2" => MenuChoice::AddCoin,
             "3" => MenuChoice::ShowPrices,
-            "4" => MenuChoice::Exit,
+            "4" => MenuChoice::SellCoin,
+            "5" => MenuChoice::ImportCsv,
+            "6" => MenuChoice::WhatIf,
+            "7" => MenuChoice::DisplayPrecision,
+            "8" => MenuChoice::Opportunities,
+            "9" => MenuChoice::RemoveCoin,
+            "10" => MenuChoice::SetAlert,
+            "11" => MenuChoice::UpdatePrice,
+            "12" => MenuChoice::MultiCurrency,
+            "13" => MenuChoice::ResetPrices,
+            "14" => MenuChoice::ExportJson,
+            "15" => MenuChoice::ImportJson,
+            "16" => MenuChoice::DiffSnapshot,
+            "17" => MenuChoice::BiggestMovers,
+            "18" => MenuChoice::MarketCaps,
+            "19" => MenuChoice::Exit,
             invalid => MenuChoice::Invalid(invalid.to_string()),
         }
     }
 }
 
+// Price threshold alert - fires when the coin trades above and/or below the given levels
+#[derive(Debug, Clone, PartialEq)]
+struct Alert {
+    coin: CryptoCoin,
+    above: Option<f64>,
+    below: Option<f64>,
+}
+
+// Current version of the export_json document layout
+const EXPORT_VERSION: u32 = 1;
+
+// Versioned export document - coins are keyed by ticker symbol
+#[derive(Debug, Serialize, Deserialize)]
+struct PortfolioExport {
+    version: u32,
+    holdings: BTreeMap<String, f64>,
+    prices: BTreeMap<String, f64>,
+}
+
+// Example USD conversion rates used by the multi-currency view
+const FIAT_RATES: [(&str, f64); 3] = [("USD", 1.0), ("EUR", 0.92), ("GBP", 0.79)];
+
 // Struct to represent the portfolio tracker
 struct PortfolioTracker{
     prices: HashMap<CryptoCoin, f64>,
     portfolio: HashMap<CryptoCoin, f64>,
+    // Average cost per unit paid for each holding
+    cost_basis: HashMap<CryptoCoin, f64>,
+    realized_pnl: f64,
+    // Decimal places used when displaying coin amounts and dollar prices/values
+    decimals_amount: usize,
+    decimals_price: usize,
+    alerts: Vec<Alert>,
+    // Price change over the last 24h in percent, per coin
+    changes_24h: HashMap<CryptoCoin, f64>,
+    // Circulating supply per coin, for market caps
+    supply: HashMap<CryptoCoin, f64>,
+    // Applied to the totals in the portfolio view
+    rounding_mode: RoundingMode,
 }
 
 impl PortfolioTracker{
     fn new() -> Self {
-        let mut prices = HashMap::new();
-
-        //Initialize price database - real world example prices
-        prices.insert(CryptoCoin::Bitcoin, 45000.0);
-        prices.insert(CryptoCoin::Ethereum, 2000.0);
-        prices.insert(CryptoCoin::Solana, 157.0);
-        prices.insert(CryptoCoin::Cardano, 0.45);
-        prices.insert(CryptoCoin::Polkadot, 10.01);
-        prices.insert(CryptoCoin::Aptos, 4.8);
-
         PortfolioTracker {
-            prices,
+            prices: Self::default_prices(),
             portfolio: HashMap::new(),
+            cost_basis: HashMap::new(),
+            realized_pnl: 0.0,
+            decimals_amount: 4,
+            decimals_price: 2,
+            alerts: Vec::new(),
+            changes_24h: CryptoCoin::all()
+                .into_iter()
+                .map(|coin| {
+                    let change = coin.default_change_24h();
+                    (coin, change)
+                })
+                .collect(),
+            supply: CryptoCoin::all()
+                .into_iter()
+                .map(|coin| {
+                    let supply = coin.default_supply();
+                    (coin, supply)
+                })
+                .collect(),
+            rounding_mode: RoundingMode::HalfUp,
+        }
+    }
+
+    // Starting price for every coin
+    fn default_prices() -> HashMap<CryptoCoin, f64> {
+        CryptoCoin::all()
+            .into_iter()
+            .map(|coin| {
+                let price = coin.default_price();
+                (coin, price)
+            })
+            .collect()
+    }
+
+    // Put every price back to its default; holdings and alerts are kept
+    fn reset_prices(&mut self) {
+        self.prices = Self::default_prices();
+    }
+
+    fn reset_prices_menu(&mut self) {
+        if !self.confirm("Reset all prices to their defaults?") {
+            println!(" Cancelled - prices unchanged.");
+            return;
+        }
+        self.reset_prices();
+        println!(" Prices reset to defaults.");
+        for message in self.check_alerts() {
+            println!(" {}", message);
         }
     }
 
@@ -107,59 +320,341 @@ impl PortfolioTracker{
         println!("1. View Portfolio");
         println!("2. Add/Update Coin");
         println!("3. Show Prices");
-        println!("4. Exit");
-        print!("Enter your choice (1-4): ");
+        println!("4. Sell Coin");
+        println!("5. Import CSV");
+        println!("6. What-If Prices");
+        println!("7. Display Precision");
+        println!("8. Opportunities");
+        println!("9. Remove Coin");
+        println!("10. Set Price Alert");
+        println!("11. Update Price");
+        println!("12. Total in USD/EUR/GBP");
+        println!("13. Reset Prices");
+        println!("14. Export JSON");
+        println!("15. Import JSON");
+        println!("16. Compare With Snapshot");
+        println!("17. Biggest Movers (24h)");
+        println!("18. Market Cap Ranking");
+        println!("19. Exit");
+        print!("Enter your choice (1-19): ");
         io::stdout().flush().unwrap();
     }
 
-    fn get_user_input(&self) -> String{
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("Failed to read input");
-        input.trim().to_string()
+    fn set_decimals_amount(&mut self, decimals: usize) {
+        self.decimals_amount = decimals;
+    }
+
+    fn set_decimals_price(&mut self, decimals: usize) {
+        self.decimals_price = decimals;
+    }
+
+    // Coin amount at the configured precision
+    fn format_amount(&self, amount: f64) -> String {
+        format!("{:.*}", self.decimals_amount, amount)
+    }
+
+    // Per-unit dollar price at the configured precision (totals use format_usd)
+    fn format_price(&self, value: f64) -> String {
+        format_usd_with(value, self.decimals_price)
+    }
+
+    // Ask a yes/no question; anything other than yes counts as no
+    fn confirm(&self, message: &str) -> bool {
+        confirm_with(message, || self.get_user_input().unwrap_or_default())
+    }
+
+    // Read one trimmed line from stdin - None once stdin is closed (EOF)
+    fn get_user_input(&self) -> Option<String> {
+        read_input_line(&mut io::stdin().lock())
     }
 
     //View portfolio with calculations
     fn view_portfolio(&self){
+        self.view_portfolio_filtered(0.0);
+    }
+
+    // Value of every holding that has a price; unpriced coins are skipped (see missing_prices)
+    fn total_value(&self) -> f64 {
+        self.portfolio
+            .iter()
+            .filter_map(|(coin, amount)| self.prices.get(coin).map(|price| amount * price))
+            .sum()
+    }
+
+    // Held coins with no price entry, which total_value can't count
+    fn missing_prices(&self) -> Vec<CryptoCoin> {
+        CryptoCoin::all()
+            .into_iter()
+            .filter(|coin| self.portfolio.contains_key(coin) && !self.prices.contains_key(coin))
+            .collect()
+    }
+
+    // Holdings whose current value is below `min_value` (by size, so shorts count too)
+    fn dust(&self, min_value: f64) -> Vec<(CryptoCoin, f64)> {
+        self.portfolio
+            .iter()
+            .filter_map(|(coin, amount)| self.prices.get(coin).map(|price| (coin.clone(), amount * price)))
+            .filter(|(_, value)| value.abs() < min_value)
+            .collect()
+    }
+
+    // Portfolio view that hides "dust" holdings worth less than `min_value`.
+    // Totals still include them; the footer says how many were hidden and their value.
+    fn view_portfolio_filtered(&self, min_value: f64){
         if self.portfolio.is_empty(){
             println!("\n Portfolio is empty. Add coins to view portfolio.");
             return;
         }
 
         println!("\n -------------- PORTFOLIO ---------------");
-        println!("{:<15} {:<10} {:<12} {:<12}", "Coin", "Amount", "Price", "Value");
-        println!("{}", "-".repeat(50));
+        println!("{:<15} {:<10} {:<12} {:<12} {:<12}", "Coin", "Amount", "Price", "Value", "Break-even");
+        // Shorts carry a negative amount, so their value counts against the total
+        println!("{}", "-".repeat(63));
 
-        let mut total_value = 0.0;
+        let total_value = self.total_value();
+        let dust = self.dust(min_value);
 
         for (coin, amount) in &self.portfolio{
             // Using Option<T> to safely get price - demonstrates null safety
 
             if let Some(price) = self.prices.get(coin){
                 let value = amount * price;
-                total_value += value;
+                if dust.iter().any(|(dust_coin, _)| dust_coin == coin) {
+                    continue;
+                }
+
+                let break_even = self
+                    .break_even(coin)
+                    .map(|p| self.format_price(p))
+                    .unwrap_or_else(|| "-".to_string());
 
                 println!(
-                    "{:<15} {:<10.4} ${:<11.2} ${:<11.2}",
+                    "{:<15} {:<10} {:<12} {:<12} {:<12}{}",
                     coin.display_name(),
-                    amount,
-                    price,
-                    value
+                    self.format_amount(*amount),
+                    self.format_price(*price),
+                    format_usd(value),
+                    break_even,
+                    if *amount < 0.0 { " SHORT" } else { "" }
                 );
             }
         }
 
-        println!("{}", "-".repeat(50));
-        println!("Total Value: ${:.2}", total_value);
+        let invested = self.total_invested();
+        println!("{}", "-".repeat(63));
+        println!("Total Value: {}", self.format_total(total_value));
+        println!("Total Invested: {}", self.format_total(invested));
+        println!("Unrealized P&L: {}", self.format_total(total_value - invested));
+        println!("Realized P&L: {}", self.format_total(self.realized_pnl));
+        if !dust.is_empty() {
+            let dust_value: f64 = dust.iter().map(|(_, value)| value).sum();
+            println!("Hidden: {} dust holding(s) worth {}", dust.len(), format_usd(dust_value));
+        }
+        let missing = self.missing_prices();
+        if !missing.is_empty() {
+            let names: Vec<&str> = missing.iter().map(|coin| coin.symbol()).collect();
+            println!("Warning: no price for {} - left out of the totals", names.join(", "));
+        }
+
+        println!("\n Allocation:");
+        print!("{}", self.render_allocation_bars(40));
+    }
+
+    // One line per priced holding with a bar of '#' sized to its share of the total
+    // value, scaled so a 100% share fills `width`. Shorts count by absolute value.
+    fn render_allocation_bars(&self, width: usize) -> String {
+        let mut values: Vec<(&CryptoCoin, f64)> = self
+            .portfolio
+            .iter()
+            .filter_map(|(coin, amount)| self.prices.get(coin).map(|price| (coin, (amount * price).abs())))
+            .collect();
+        let total: f64 = values.iter().map(|(_, value)| value).sum();
+        if total <= 0.0 {
+            return "  No holdings with value to chart\n".to_string();
+        }
+
+        values.sort_by_key(|(coin, _)| coin.display_name());
+        values
+            .iter()
+            .map(|(coin, value)| {
+                let share = value / total;
+                let bar = "#".repeat((share * width as f64).round() as usize);
+                format!("{:<15} {:<width$} {:>5.1}%\n", coin.display_name(), bar, share * 100.0, width = width)
+            })
+            .collect()
+    }
+
+    // Dollar total rounded to cents with the configured rounding mode
+    fn format_total(&self, value: f64) -> String {
+        format_usd(round_value(value, self.rounding_mode, 2))
+    }
+
+    // Average cost per unit - the price at which the position breaks even
+    fn break_even(&self, coin: &CryptoCoin) -> Option<f64> {
+        let amount = self.portfolio.get(coin).copied().unwrap_or(0.0);
+        if amount == 0.0 {
+            return None;
+        }
+        self.cost_basis.get(coin).copied()
+    }
+
+    // Sum of amount * average cost across all holdings
+    fn total_invested(&self) -> f64 {
+        self.portfolio
+            .iter()
+            .map(|(coin, amount)| amount * self.cost_basis.get(coin).copied().unwrap_or(0.0))
+            .sum()
+    }
+
+    // Trade `amount` of a coin at `price`; a negative amount sells or goes short.
+    // Adding to a position folds the price into the average cost per unit. Trading
+    // against it realizes (price - average cost) on the part closed and keeps the
+    // average, and a trade that flips through zero starts the new side at `price`.
+    fn record_purchase(&mut self, coin: CryptoCoin, amount: f64, price: f64) {
+        let held = self.portfolio.get(&coin).copied().unwrap_or(0.0);
+        let avg_cost = self.cost_basis.get(&coin).copied().unwrap_or(0.0);
+        let total = held + amount;
+        let reduces = held * amount < 0.0;
+        if reduces {
+            let closed = amount.abs().min(held.abs());
+            self.realized_pnl += (price - avg_cost) * closed * held.signum();
+        }
+        if total == 0.0 {
+            self.remove_coin(&coin);
+            return;
+        }
+
+        let new_cost = if !reduces {
+            (held * avg_cost + amount * price) / total
+        } else if total * held > 0.0 {
+            avg_cost
+        } else {
+            price
+        };
+        self.cost_basis.insert(coin.clone(), new_cost);
+        self.portfolio.insert(coin, total);
+    }
+
+    // Replace a holding outright - the new amount is treated as bought at `price`
+    fn set_holding(&mut self, coin: CryptoCoin, amount: f64, price: f64) {
+        self.cost_basis.insert(coin.clone(), price);
+        self.portfolio.insert(coin, amount);
+    }
+
+    // Drop a holding entirely, returning the amount that was held
+    fn remove_coin(&mut self, coin: &CryptoCoin) -> Option<f64> {
+        self.cost_basis.remove(coin);
+        self.portfolio.remove(coin)
+    }
+
+    fn remove_coin_menu(&mut self) {
+        println!("\n Remove Coin");
+        print!("Enter coin name: ");
+        io::stdout().flush().unwrap();
+        let coin = match CryptoCoin::from_string(&self.get_user_input().unwrap_or_default()) {
+            Some(c) => c,
+            None => {
+                println!("Invalid coin name. Try again.");
+                return;
+            }
+        };
+
+        let Some(amount) = self.portfolio.get(&coin).copied() else {
+            println!(" You don't hold any {}.", coin.symbol());
+            return;
+        };
+        let message = format!("Remove all {} {} from your portfolio?", self.format_amount(amount), coin.symbol());
+        if !self.confirm(&message) {
+            println!(" Cancelled - holdings unchanged.");
+            return;
+        }
+        if self.remove_coin(&coin).is_some() {
+            println!(" Removed {} from your portfolio.", coin.display_name());
+        }
+    }
+
+    // Sell part of a holding and book the realized gain against the average cost
+    fn sell_coin(&mut self, coin: CryptoCoin, amount: f64, sale_price: f64) -> Result<f64, String> {
+        if amount <= 0.0 {
+            return Err("Sell amount must be positive".to_string());
+        }
+        let held = self.portfolio.get(&coin).copied().unwrap_or(0.0);
+        if amount > held {
+            return Err(format!("Cannot sell {} {}: only {} held", self.format_amount(amount), coin.symbol(), self.format_amount(held)));
+        }
+
+        let avg_cost = self.cost_basis.get(&coin).copied().unwrap_or(0.0);
+        let gain = (sale_price - avg_cost) * amount;
+        self.realized_pnl += gain;
+
+        let remaining = held - amount;
+        if remaining > 0.0 {
+            self.portfolio.insert(coin, remaining);
+        } else {
+            self.portfolio.remove(&coin);
+            self.cost_basis.remove(&coin);
+        }
+        Ok(gain)
+    }
+
+    // Ask whether an entered amount is long (+1.0) or short (-1.0); defaults to long
+    fn prompt_position_sign(&self) -> f64 {
+        print!("Is this a (L)ong or (S)hort position? [L]: ");
+        io::stdout().flush().unwrap();
+        match self.get_user_input().unwrap_or_default().to_lowercase().as_str() {
+            "s" | "short" => -1.0,
+            _ => 1.0,
+        }
+    }
+
+    // Coin quantity to add, typed either directly or as a USD value converted at the
+    // current price. Prints the problem and returns None on bad input.
+    fn prompt_amount(&self, coin: &CryptoCoin) -> Option<f64> {
+        print!("Enter a (C)oin amount or a (F)iat value in USD? [C]: ");
+        io::stdout().flush().unwrap();
+        let in_fiat = matches!(self.get_user_input().unwrap_or_default().to_lowercase().as_str(), "f" | "fiat");
+
+        print!("{}", if in_fiat { "Enter USD value: " } else { "Enter amount: " });
+        io::stdout().flush().unwrap();
+        let input = self.get_user_input().unwrap_or_default();
+        let value = match parse_positive_f64(&input) {
+            Ok(v) => v,
+            Err(e) => {
+                println!(" Invalid amount: {}", e);
+                return None;
+            }
+        };
+        if !in_fiat {
+            return Some(value);
+        }
+
+        match self.fiat_to_amount(coin, value) {
+            Some(amount) => {
+                println!(" {} buys {} {}", format_usd(value), self.format_amount(amount), coin.symbol());
+                Some(amount)
+            }
+            None => {
+                println!(" No price for {} - enter a coin amount instead.", coin.symbol());
+                None
+            }
+        }
+    }
+
+    // How many coins `fiat` USD buys at the current price; None without a usable price
+    fn fiat_to_amount(&self, coin: &CryptoCoin, fiat: f64) -> Option<f64> {
+        let price = self.prices.get(coin).copied().filter(|price| *price > 0.0)?;
+        Some(fiat / price)
     }
 
     //Add/Update coin
     fn add_coin(&mut self){
         println!("\n Add/Update Coin");
-        println!("Available coins: Bitcoin, Ethereum, Solana, Cardano, Polkadot, Aptos");
+        let available: Vec<String> = CryptoCoin::all().iter().map(|coin| coin.aliases().join("/")).collect();
+        println!("Available coins: {}", available.join(", "));
         print!("Enter coin name: ");
         io::stdout().flush().unwrap();
 
-        let coin_input = self.get_user_input();
+        let coin_input = self.get_user_input().unwrap_or_default();
 
         //using Option<T> for safe conversion - demonstrates null safety
         let coin = match CryptoCoin::from_string(&coin_input){
@@ -173,65 +668,525 @@ impl PortfolioTracker{
         // Check if the coin already exists in the portfolio
         let existing_amount = self.portfolio.get(&coin).copied().unwrap_or(0.0);
 
-        if existing_amount > 0.0 {
-            println!("You currently own {:.4} {}", existing_amount, coin.symbol());
+        if existing_amount != 0.0 {
+            println!("You currently own {} {}", self.format_amount(existing_amount), coin.symbol());
             print!("Do you want to (R)replace or (A)dd to existing amount? ");
             io::stdout().flush().unwrap();
 
-            let choice = self.get_user_input().to_lowercase();
+            let choice = self.get_user_input().unwrap_or_default().to_lowercase();
             let operation = match choice.as_str() {
                 "a" | "add" => PortfolioOperation::Add(existing_amount),
-                "r" | "replace" | "" => PortfolioOperation::Replace(0.0),
+                "r" | "replace" | "" => PortfolioOperation::Replace,
                 _ => {
                     println!("Invalid choice. Defaulting to replace.");
-                    PortfolioOperation::Replace(0.0)
+                    PortfolioOperation::Replace
                 }
             };
 
-            print!("Enter amount: ");
-            io::stdout().flush().unwrap();
+            let Some(amount) = self.prompt_amount(&coin) else {
+                return;
+            };
+            let amount = amount * self.prompt_position_sign();
 
-            let amount_input = self.get_user_input();
-            let amount: f64 = match amount_input.parse() {
-                Ok(a) if a > 0.0 => a,
-                _ => {
-                    println!(" Invalid amount. Please enter a positive number.");
+            if matches!(operation, PortfolioOperation::Replace) {
+                let message = format!(
+                    "Replace your {} {} with {}?",
+                    self.format_amount(existing_amount),
+                    coin.symbol(),
+                    self.format_amount(amount)
+                );
+                if !self.confirm(&message) {
+                    println!(" Cancelled - holdings unchanged.");
                     return;
                 }
-            };
+            }
 
             // Pattern matching on the operation enum
             let final_amount = match operation {
                 PortfolioOperation::Add(existing) => existing + amount,
-                PortfolioOperation::Replace(_) => amount,
+                PortfolioOperation::Replace => amount,
             };
 
-            self.portfolio.insert(coin.clone(), final_amount);
+            let price = self.prices.get(&coin).copied().unwrap_or(0.0);
+            match operation {
+                PortfolioOperation::Add(_) => self.record_purchase(coin.clone(), amount, price),
+                PortfolioOperation::Replace => self.set_holding(coin.clone(), amount, price),
+            }
 
             match operation {
                 PortfolioOperation::Add(_) => {
-                    println!(" Added {:.4} {} to your portfolio!", amount, coin.symbol());
-                    println!("Total {} holdings: {:.4}", coin.symbol(), final_amount);
+                    println!(" Added {} {} to your portfolio!", self.format_amount(amount), coin.symbol());
+                    println!("Total {} holdings: {}", coin.symbol(), self.format_amount(final_amount));
                 }
-                PortfolioOperation::Replace(_) => {
-                    println!(" Updated {} holdings to {:.4}!", coin.symbol(), final_amount);
+                PortfolioOperation::Replace => {
+                    println!(" Updated {} holdings to {}!", coin.symbol(), self.format_amount(final_amount));
                 }
             }
         } else {
-            print!("Enter amount: ");
-            io::stdout().flush().unwrap();
+            let Some(amount) = self.prompt_amount(&coin) else {
+                return;
+            };
+            let amount = amount * self.prompt_position_sign();
 
-            let amount_input = self.get_user_input();
-            let amount: f64 = match amount_input.parse() {
-                Ok(a) if a > 0.0 => a,
-                _ => {
-                    println!(" Invalid amount. Please enter a positive number.");
+            let price = self.prices.get(&coin).copied().unwrap_or(0.0);
+            self.record_purchase(coin.clone(), amount, price);
+            println!(" Added {} {} to your portfolio!", self.format_amount(amount), coin.symbol());
+        }
+    }
+
+    // Sell coin menu - sale price defaults to the current price
+    fn sell_coin_menu(&mut self) {
+        println!("\n Sell Coin");
+        print!("Enter coin name: ");
+        io::stdout().flush().unwrap();
+
+        let coin = match CryptoCoin::from_string(&self.get_user_input().unwrap_or_default()) {
+            Some(c) => c,
+            None => {
+                println!("Invalid coin name. Try again.");
+                return;
+            }
+        };
+
+        print!("Enter amount to sell: ");
+        io::stdout().flush().unwrap();
+        let amount = match parse_positive_f64(&self.get_user_input().unwrap_or_default()) {
+            Ok(a) => a,
+            Err(e) => {
+                println!(" Invalid amount: {}", e);
+                return;
+            }
+        };
+
+        let current_price = self.prices.get(&coin).copied().unwrap_or(0.0);
+        print!("Enter sale price (blank for {}): ", self.format_price(current_price));
+        io::stdout().flush().unwrap();
+        let price_input = self.get_user_input().unwrap_or_default();
+        let sale_price: f64 = if price_input.is_empty() {
+            current_price
+        } else {
+            match parse_positive_f64(&price_input) {
+                Ok(p) => p,
+                Err(e) => {
+                    println!(" Invalid price: {}", e);
                     return;
                 }
+            }
+        };
+
+        match self.sell_coin(coin.clone(), amount, sale_price) {
+            Ok(gain) => {
+                println!(" Sold {} {} at {}", self.format_amount(amount), coin.symbol(), self.format_price(sale_price));
+                println!("Realized gain: {} (total realized: {})", self.format_price(gain), self.format_price(self.realized_pnl));
+            }
+            Err(e) => println!(" {}", e),
+        }
+    }
+
+    // Total portfolio value using override prices where given, stored prices otherwise
+    fn simulate(&self, overrides: &HashMap<CryptoCoin, f64>) -> f64 {
+        let values = self.portfolio.iter().filter_map(|(coin, amount)| {
+            overrides.get(coin).or_else(|| self.prices.get(coin)).map(|price| amount * price)
+        });
+        summarize(values).sum
+    }
+
+    fn what_if_menu(&self) {
+        println!("\n What-If Price Simulation");
+        print!("Enter coin name: ");
+        io::stdout().flush().unwrap();
+        let coin = match CryptoCoin::from_string(&self.get_user_input().unwrap_or_default()) {
+            Some(c) => c,
+            None => {
+                println!("Invalid coin name. Try again.");
+                return;
+            }
+        };
+
+        print!("Enter hypothetical {} price: ", coin.symbol());
+        io::stdout().flush().unwrap();
+        let price = match parse_positive_f64(&self.get_user_input().unwrap_or_default()) {
+            Ok(p) => p,
+            Err(e) => {
+                println!(" Invalid price: {}", e);
+                return;
+            }
+        };
+
+        let current = self.simulate(&HashMap::new());
+        let projected = self.simulate(&HashMap::from([(coin.clone(), price)]));
+        println!("Current total:   {}", self.format_price(current));
+        println!("Projected total: {} (change: {})", self.format_price(projected), self.format_price(projected - current));
+    }
+
+    fn precision_menu(&mut self) {
+        println!("\n Display Precision (currently {} amount / {} price decimals)", self.decimals_amount, self.decimals_price);
+        print!("Decimals for amounts (blank to keep): ");
+        io::stdout().flush().unwrap();
+        match parse_decimals(&self.get_user_input().unwrap_or_default()) {
+            Ok(Some(decimals)) => self.set_decimals_amount(decimals),
+            Ok(None) => {}
+            Err(e) => println!(" Invalid decimals: {}", e),
+        }
+        print!("Decimals for prices (blank to keep): ");
+        io::stdout().flush().unwrap();
+        match parse_decimals(&self.get_user_input().unwrap_or_default()) {
+            Ok(Some(decimals)) => self.set_decimals_price(decimals),
+            Ok(None) => {}
+            Err(e) => println!(" Invalid decimals: {}", e),
+        }
+        print!("Rounding for totals - up, even or truncate (currently {:?}, blank to keep): ", self.rounding_mode);
+        io::stdout().flush().unwrap();
+        if let Some(mode) = RoundingMode::from_string(&self.get_user_input().unwrap_or_default()) {
+            self.rounding_mode = mode;
+        }
+        println!(" Now showing {} amount / {} price decimals, totals rounded {:?}", self.decimals_amount, self.decimals_price, self.rounding_mode);
+    }
+
+    // Holdings and prices as a versioned JSON document
+    fn export_json(&self) -> String {
+        let by_symbol = |map: &HashMap<CryptoCoin, f64>| {
+            map.iter().map(|(coin, value)| (coin.symbol().to_string(), *value)).collect()
+        };
+        let export = PortfolioExport {
+            version: EXPORT_VERSION,
+            holdings: by_symbol(&self.portfolio),
+            prices: by_symbol(&self.prices),
+        };
+        serde_json::to_string_pretty(&export).unwrap()
+    }
+
+    // Load a document written by export_json. The version is checked before anything
+    // else, and nothing changes unless the whole document is valid. Holdings are replaced,
+    // with their cost basis reset to the imported price; prices are updated per coin.
+    fn import_json(&mut self, json: &str) -> Result<(), String> {
+        let document: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {}", e))?;
+        match document.get("version").and_then(|v| v.as_u64()) {
+            Some(version) if version == EXPORT_VERSION as u64 => {}
+            Some(version) => return Err(format!("Unsupported export version {}", version)),
+            None => return Err("Missing export version".to_string()),
+        }
+        let export: PortfolioExport = serde_json::from_value(document).map_err(|e| format!("Invalid export: {}", e))?;
+
+        let by_coin = |map: BTreeMap<String, f64>| -> Result<HashMap<CryptoCoin, f64>, String> {
+            map.into_iter()
+                .map(|(symbol, value)| {
+                    CryptoCoin::from_string(&symbol)
+                        .map(|coin| (coin, value))
+                        .ok_or_else(|| format!("Unknown coin '{}'", symbol))
+                })
+                .collect()
+        };
+        let holdings = by_coin(export.holdings)?;
+        let prices = by_coin(export.prices)?;
+
+        self.prices.extend(prices);
+        self.portfolio.clear();
+        self.cost_basis.clear();
+        for (coin, amount) in holdings {
+            let price = self.prices.get(&coin).copied().unwrap_or(0.0);
+            self.set_holding(coin, amount, price);
+        }
+        Ok(())
+    }
+
+    fn export_json_menu(&self) {
+        print!("Enter JSON path: ");
+        io::stdout().flush().unwrap();
+        let path = self.get_user_input().unwrap_or_default();
+        match std::fs::write(&path, self.export_json()) {
+            Ok(()) => println!(" Exported {} holding(s) to {}", self.portfolio.len(), path),
+            Err(e) => println!(" Could not write {}: {}", path, e),
+        }
+    }
+
+    fn import_json_menu(&mut self) {
+        print!("Enter JSON path: ");
+        io::stdout().flush().unwrap();
+        let path = self.get_user_input().unwrap_or_default();
+        let result = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read {}: {}", path, e))
+            .and_then(|json| self.import_json(&json));
+        match result {
+            Ok(()) => println!(" Imported {} holding(s) from {}", self.portfolio.len(), path),
+            Err(e) => println!(" {}", e),
+        }
+    }
+
+    // (coin, old amount, new amount) for every coin whose holding differs between self (old)
+    // and other (new). A coin held on only one side reports 0.0 for the other.
+    fn diff(&self, other: &PortfolioTracker) -> Vec<(CryptoCoin, f64, f64)> {
+        CryptoCoin::all()
+            .into_iter()
+            .filter_map(|coin| {
+                let old = self.portfolio.get(&coin).copied().unwrap_or(0.0);
+                let new = other.portfolio.get(&coin).copied().unwrap_or(0.0);
+                (old != new).then_some((coin, old, new))
+            })
+            .collect()
+    }
+
+    // Load a snapshot written by Export JSON and show what changed since
+    fn diff_snapshot_menu(&self) {
+        print!("Enter snapshot JSON path: ");
+        io::stdout().flush().unwrap();
+        let path = self.get_user_input().unwrap_or_default();
+        let mut snapshot = PortfolioTracker::new();
+        let result = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read {}: {}", path, e))
+            .and_then(|json| snapshot.import_json(&json));
+        if let Err(e) = result {
+            println!(" {}", e);
+            return;
+        }
+
+        let changes = snapshot.diff(self);
+        if changes.is_empty() {
+            println!(" No changes since {}", path);
+            return;
+        }
+        println!("\n Changes since {}:", path);
+        for (coin, old, new) in changes {
+            println!(" {}: {} -> {}", coin.display_name(), self.format_amount(old), self.format_amount(new));
+        }
+    }
+
+    // Bulk-load holdings from a `symbol,amount` CSV file
+    fn import_csv(&mut self, path: &str) -> Result<usize, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path, e))?;
+        Ok(self.import_csv_data(&data))
+    }
+
+    // Each valid row is added at the current price; malformed rows are reported and skipped
+    fn import_csv_data(&mut self, data: &str) -> usize {
+        let mut imported = 0;
+
+        for (line_no, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || (line_no == 0 && line.to_lowercase().starts_with("symbol")) {
+                continue;
+            }
+
+            let row = match line.split_once(',') {
+                Some((symbol, amount)) => CryptoCoin::from_string(symbol.trim())
+                    .zip(parse_positive_f64(amount).ok()),
+                None => None,
             };
 
-            self.portfolio.insert(coin.clone(), amount);
-            println!(" Added {:.4} {} to your portfolio!", amount, coin.symbol());
+            match row {
+                Some((coin, amount)) => {
+                    let price = self.prices.get(&coin).copied().unwrap_or(0.0);
+                    self.record_purchase(coin, amount, price);
+                    imported += 1;
+                }
+                None => println!(" Skipping line {}: '{}'", line_no + 1, line),
+            }
+        }
+
+        imported
+    }
+
+    // Supported coins (those with a price) that aren't in the portfolio, by display name
+    fn coins_not_held(&self) -> Vec<CryptoCoin> {
+        let mut coins: Vec<CryptoCoin> = CryptoCoin::all()
+            .into_iter()
+            .filter(|coin| self.prices.contains_key(coin) && !self.portfolio.contains_key(coin))
+            .collect();
+        coins.sort_by_key(|coin| coin.display_name().to_string());
+        coins
+    }
+
+    fn show_opportunities(&self) {
+        let coins = self.coins_not_held();
+        if coins.is_empty() {
+            println!("\n You already hold every supported coin!");
+            return;
+        }
+
+        println!("\n === OPPORTUNITIES (coins not held) ===");
+        println!("{:<20} {:<12}", "Coin", "Price (USD)");
+        println!("{}", "-".repeat(33));
+        for coin in coins {
+            if let Some(price) = self.prices.get(&coin) {
+                println!("{:<20} {:<12}", coin.display_name(), self.format_price(*price));
+            }
+        }
+    }
+
+    // Messages for every alert whose threshold the current price has crossed
+    fn check_alerts(&self) -> Vec<String> {
+        let mut triggered = Vec::new();
+        for alert in &self.alerts {
+            let Some(price) = self.prices.get(&alert.coin) else {
+                continue;
+            };
+            if let Some(above) = alert.above.filter(|above| price > above) {
+                triggered.push(format!(
+                    "ALERT: {} is {} - above {}",
+                    alert.coin.symbol(),
+                    self.format_price(*price),
+                    self.format_price(above)
+                ));
+            }
+            if let Some(below) = alert.below.filter(|below| price < below) {
+                triggered.push(format!(
+                    "ALERT: {} is {} - below {}",
+                    alert.coin.symbol(),
+                    self.format_price(*price),
+                    self.format_price(below)
+                ));
+            }
+        }
+        triggered
+    }
+
+    // Set a coin's price, then report any alerts it triggers
+    fn update_price(&mut self, coin: CryptoCoin, price: f64) -> Vec<String> {
+        self.prices.insert(coin, price);
+        self.check_alerts()
+    }
+
+    // Read an optional threshold: blank means "no alert on this side"
+    fn prompt_threshold(&self, label: &str) -> Result<Option<f64>, String> {
+        print!("Alert when price goes {} (blank to skip): ", label);
+        io::stdout().flush().unwrap();
+        let input = self.get_user_input().unwrap_or_default();
+        if input.is_empty() {
+            return Ok(None);
+        }
+        parse_positive_f64(&input)
+            .map(Some)
+            .map_err(|e| format!("Invalid threshold: {}", e))
+    }
+
+    fn set_alert_menu(&mut self) {
+        println!("\n Set Price Alert");
+        print!("Enter coin name: ");
+        io::stdout().flush().unwrap();
+        let coin = match CryptoCoin::from_string(&self.get_user_input().unwrap_or_default()) {
+            Some(c) => c,
+            None => {
+                println!("Invalid coin name. Try again.");
+                return;
+            }
+        };
+
+        let thresholds = self
+            .prompt_threshold("above")
+            .and_then(|above| self.prompt_threshold("below").map(|below| (above, below)));
+        match thresholds {
+            Ok((None, None)) => println!(" No thresholds entered - alert not created."),
+            Ok((above, below)) => {
+                self.alerts.push(Alert { coin: coin.clone(), above, below });
+                println!(" Alert set for {}", coin.display_name());
+                for message in self.check_alerts() {
+                    println!(" {}", message);
+                }
+            }
+            Err(e) => println!(" {}", e),
+        }
+    }
+
+    fn update_price_menu(&mut self) {
+        println!("\n Update Price");
+        print!("Enter coin name: ");
+        io::stdout().flush().unwrap();
+        let coin = match CryptoCoin::from_string(&self.get_user_input().unwrap_or_default()) {
+            Some(c) => c,
+            None => {
+                println!("Invalid coin name. Try again.");
+                return;
+            }
+        };
+
+        print!("Enter new {} price: ", coin.symbol());
+        io::stdout().flush().unwrap();
+        let price = match parse_positive_f64(&self.get_user_input().unwrap_or_default()) {
+            Ok(p) => p,
+            Err(e) => {
+                println!(" Invalid price: {}", e);
+                return;
+            }
+        };
+
+        println!(" {} price set to {}", coin.symbol(), self.format_price(price));
+        for message in self.update_price(coin, price) {
+            println!(" {}", message);
+        }
+    }
+
+    // USD total converted with each rate; non-positive or non-finite rates are skipped
+    fn total_in_currencies(&self, rates: &HashMap<String, f64>) -> HashMap<String, f64> {
+        let total_usd = self.total_value();
+        rates
+            .iter()
+            .filter(|(_, rate)| rate.is_finite() && **rate > 0.0)
+            .map(|(currency, rate)| (currency.clone(), total_usd * rate))
+            .collect()
+    }
+
+    fn show_multi_currency_totals(&self) {
+        let rates: HashMap<String, f64> = FIAT_RATES
+            .iter()
+            .map(|(currency, rate)| (currency.to_string(), *rate))
+            .collect();
+        let totals = self.total_in_currencies(&rates);
+
+        println!("\n === TOTAL VALUE BY CURRENCY ===");
+        for (currency, _) in FIAT_RATES {
+            if let Some(total) = totals.get(currency) {
+                println!("{:<5} {}", currency, format_usd(*total).replacen('$', "", 1));
+            }
+        }
+    }
+
+    // Held coins with the highest and lowest 24h change - (None, None) when nothing is held
+    fn biggest_movers(&self) -> (Option<CryptoCoin>, Option<CryptoCoin>) {
+        let moves: Vec<(CryptoCoin, f64)> = CryptoCoin::all()
+            .into_iter()
+            .filter(|coin| self.portfolio.get(coin).is_some_and(|&amount| amount != 0.0))
+            .filter_map(|coin| self.changes_24h.get(&coin).map(|&change| (coin, change)))
+            .collect();
+        let gainer = moves.iter().max_by(|a, b| a.1.total_cmp(&b.1)).map(|(coin, _)| coin.clone());
+        let loser = moves.iter().min_by(|a, b| a.1.total_cmp(&b.1)).map(|(coin, _)| coin.clone());
+        (gainer, loser)
+    }
+
+    fn show_biggest_movers(&self) {
+        let (Some(gainer), Some(loser)) = self.biggest_movers() else {
+            println!("\n No holdings to compare - add some coins first.");
+            return;
+        };
+        println!("\n === BIGGEST MOVERS (24h) ===");
+        println!(" Top gainer: {} {:+.2}%", gainer.display_name(), self.changes_24h[&gainer]);
+        println!(" Top loser:  {} {:+.2}%", loser.display_name(), self.changes_24h[&loser]);
+    }
+
+    // Price times circulating supply - None without both
+    fn market_cap(&self, coin: &CryptoCoin) -> Option<f64> {
+        Some(self.prices.get(coin)? * self.supply.get(coin)?)
+    }
+
+    // Coins with a known market cap, largest first
+    fn market_cap_ranking(&self) -> Vec<(CryptoCoin, f64)> {
+        let mut ranking: Vec<(CryptoCoin, f64)> = CryptoCoin::all()
+            .into_iter()
+            .filter_map(|coin| self.market_cap(&coin).map(|cap| (coin, cap)))
+            .collect();
+        ranking.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranking
+    }
+
+    fn show_market_caps(&self) {
+        println!("\n === MARKET CAP RANKING ===");
+        println!(" {:<4} {:<16} {:>26}", "Rank", "Coin", "Market Cap");
+        let ranking = self.market_cap_ranking();
+        for (rank, (coin, cap)) in ranking.iter().enumerate() {
+            println!(" {:<4} {:<16} {:>26}", rank + 1, coin.display_name(), format_usd_with(*cap, 0));
+        }
+        for coin in CryptoCoin::all().into_iter().filter(|coin| ranking.iter().all(|(ranked, _)| ranked != coin)) {
+            println!(" {} is unranked - no price or supply", coin.display_name());
         }
     }
 
@@ -246,17 +1201,61 @@ impl PortfolioTracker{
         sorted_prices.sort_by_key(|(coin, _)| coin.display_name());
 
         for (coin, price) in sorted_prices {
-            println!("{:<20} ${:<11.2}", coin.display_name(), price);
+            println!("{:<20} {:<12}", coin.display_name(), self.format_price(*price));
         }
     }
 
+    // Run one text command and return the result instead of printing it:
+    //   add <coin> <amount> | remove <coin> | price <coin> <price> | total
+    fn run_command(&mut self, line: &str) -> String {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let parse_coin = |symbol: &str| {
+            CryptoCoin::from_string(symbol).ok_or_else(|| format!("Error: unknown coin '{}'", symbol))
+        };
+        let error = |e: String| format!("Error: {}", e);
+
+        let result = match parts.as_slice() {
+            ["add", symbol, amount] => parse_coin(symbol).and_then(|coin| {
+                // Signed, since a negative amount opens or adds to a short
+                let amount = parse_finite_f64(amount).map_err(error)?;
+                if amount == 0.0 {
+                    return Err("Error: amount must be non-zero".to_string());
+                }
+                let price = self.prices.get(&coin).copied().unwrap_or(0.0);
+                self.record_purchase(coin.clone(), amount, price);
+                let held = self.portfolio.get(&coin).copied().unwrap_or(0.0);
+                Ok(format!("Added {} {} (now {})", self.format_amount(amount), coin.symbol(), self.format_amount(held)))
+            }),
+            ["remove", symbol] => parse_coin(symbol).map(|coin| match self.remove_coin(&coin) {
+                Some(amount) => format!("Removed {} {}", self.format_amount(amount), coin.symbol()),
+                None => format!("No {} holding to remove", coin.symbol()),
+            }),
+            ["price", symbol, price] => parse_coin(symbol).and_then(|coin| {
+                let price = parse_positive_f64(price).map_err(error)?;
+                let alerts = self.update_price(coin.clone(), price);
+                let mut out = format!("{} price set to {}", coin.symbol(), self.format_price(price));
+                for alert in alerts {
+                    out.push_str(&format!("\n{}", alert));
+                }
+                Ok(out)
+            }),
+            ["total"] => Ok(format!("Total Value: {}", format_usd(self.total_value()))),
+            _ => Err(format!("Error: unknown command '{}'", line.trim())),
+        };
+
+        result.unwrap_or_else(|e| e)
+    }
+
     // Main program loop
     fn run(&mut self) {
         println!(" Welcome to the Crypto Portfolio Tracker!");
 
         loop {
             self.display_menu();
-            let input = self.get_user_input();
+            let Some(input) = self.get_user_input() else {
+                println!("\n\n Input closed - exiting Crypto Portfolio Tracker. Goodbye!");
+                break;
+            };
             let choice = MenuChoice::from_input(&input);
 
             // Pattern matching on MenuChoice enum - demonstrates match expression
@@ -264,13 +1263,36 @@ impl PortfolioTracker{
                 MenuChoice::ViewPortfolio => self.view_portfolio(),
                 MenuChoice::AddCoin => self.add_coin(),
                 MenuChoice::ShowPrices => self.show_prices(),
+                MenuChoice::SellCoin => self.sell_coin_menu(),
+                MenuChoice::WhatIf => self.what_if_menu(),
+                MenuChoice::DisplayPrecision => self.precision_menu(),
+                MenuChoice::Opportunities => self.show_opportunities(),
+                MenuChoice::RemoveCoin => self.remove_coin_menu(),
+                MenuChoice::ImportCsv => {
+                    print!("Enter CSV path: ");
+                    io::stdout().flush().unwrap();
+                    let path = self.get_user_input().unwrap_or_default();
+                    match self.import_csv(&path) {
+                        Ok(count) => println!(" Imported {} holding(s) from {}", count, path),
+                        Err(e) => println!(" {}", e),
+                    }
+                }
+                MenuChoice::SetAlert => self.set_alert_menu(),
+                MenuChoice::UpdatePrice => self.update_price_menu(),
+                MenuChoice::MultiCurrency => self.show_multi_currency_totals(),
+                MenuChoice::ResetPrices => self.reset_prices_menu(),
+                MenuChoice::ExportJson => self.export_json_menu(),
+                MenuChoice::ImportJson => self.import_json_menu(),
+                MenuChoice::DiffSnapshot => self.diff_snapshot_menu(),
+                MenuChoice::BiggestMovers => self.show_biggest_movers(),
+                MenuChoice::MarketCaps => self.show_market_caps(),
                 MenuChoice::Exit => {
                     println!("\n Thank you for using Crypto Portfolio Tracker!");
                     println!("Happy trading! ");
                     break;
                 }
                 MenuChoice::Invalid(ref invalid_input) => {
-                    println!(" Invalid choice: '{}'. Please enter 1-4.", invalid_input);
+                    println!(" Invalid choice: '{}'. Please enter 1-19.", invalid_input);
                 }
 
             }
@@ -278,15 +1300,106 @@ impl PortfolioTracker{
             // Optional: Add a small pause for better UX
             if !matches!(choice, MenuChoice::Exit) {
                 println!("\nPress Enter to continue...");
-                self.get_user_input();
+                if self.get_user_input().is_none() {
+                    println!("\n Input closed - exiting Crypto Portfolio Tracker. Goodbye!");
+                    break;
+                }
             }
         }
     }
 }
 
+// Most decimal places the precision menu accepts - f64 holds about 15 significant digits
+const MAX_DECIMALS: usize = 12;
+
+// Decimal places typed at the precision menu: None for blank (keep the current setting),
+// otherwise a whole number from 0 to MAX_DECIMALS
+fn parse_decimals(input: &str) -> Result<Option<usize>, String> {
+    let decimals = match input.trim() {
+        "" => return Ok(None),
+        "0" => 0,
+        trimmed => parse_positive_u64(trimmed)? as usize,
+    };
+    if decimals > MAX_DECIMALS {
+        return Err(format!("'{}' is more than {} decimals", input.trim(), MAX_DECIMALS));
+    }
+    Ok(Some(decimals))
+}
+
+// Dollar amount with thousands separators, e.g. "$1,234.56"
+fn format_usd(value: f64) -> String {
+    format_usd_with(value, 2)
+}
+
+fn format_usd_with(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = match formatted.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (formatted.as_str(), None),
+    };
+
+    // Insert a comma before every group of three digits, counting from the right
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    let sign = if value < 0.0 && formatted.chars().any(|c| c != '0' && c != '.') { "-" } else { "" };
+    match fraction {
+        Some(fraction) => format!("{}${}.{}", sign, grouped, fraction),
+        None => format!("{}${}", sign, grouped),
+    }
+}
+
+// Round to `decimals` places. The value is first snapped to 6 places past the target
+// so binary noise doesn't decide ties - 2.675 is stored as 2.67499999... but is
+// treated as exactly half way. HalfUp rounds ties away from zero.
+fn round_value(value: f64, mode: RoundingMode, decimals: usize) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    let scaled = (value * factor * 1e6).round() / 1e6;
+    let rounded = match mode {
+        RoundingMode::HalfUp => scaled.round(),
+        RoundingMode::HalfEven => scaled.round_ties_even(),
+        RoundingMode::Truncate => scaled.trunc(),
+    };
+    rounded / factor
+}
+
+// Read a line and trim it. Returns None at end of input (read_line gives Ok(0))
+// or on a read error, so callers can stop instead of looping on empty input.
+fn read_input_line<R: io::BufRead>(reader: &mut R) -> Option<String> {
+    let mut input = String::new();
+    match reader.read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input.trim().to_string()),
+    }
+}
+
+// Prompt with "(y/n)" and read the answer from `read_input`, so tests can supply it.
+// Empty or unrecognised answers default to "no".
+fn confirm_with<F: FnOnce() -> String>(message: &str, read_input: F) -> bool {
+    print!("{} (y/n): ", message);
+    io::stdout().flush().unwrap();
+    matches!(read_input().trim().to_lowercase().as_str(), "y" | "yes")
+}
+
 fn main() {
     // Create and run the portfolio tracker
     let mut tracker = PortfolioTracker::new();
+
+    // `--script` reads one command per line from stdin instead of showing the menu
+    if std::env::args().any(|arg| arg == "--script") {
+        while let Some(line) = read_input_line(&mut io::stdin().lock()) {
+            if !line.is_empty() {
+                println!("{}", tracker.run_command(&line));
+            }
+        }
+        return;
+    }
+
     tracker.run();
 }
 
@@ -326,9 +1439,464 @@ mod tests {
         assert_eq!(CryptoCoin::from_string("invalid"), None);
     }
 
+    #[test]
+    fn test_crypto_coin_all() {
+        let coins = CryptoCoin::all();
+        assert_eq!(coins.len(), 6);
+        assert!(coins.contains(&CryptoCoin::Aptos));
+        assert_eq!(PortfolioTracker::new().prices.len(), coins.len());
+    }
+
+    #[test]
+    fn test_reset_prices_restores_defaults() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.update_price(CryptoCoin::Solana, 1.0);
+        tracker.prices.remove(&CryptoCoin::Aptos);
+
+        tracker.reset_prices();
+        assert_eq!(tracker.prices[&CryptoCoin::Solana], 157.0);
+        assert_eq!(tracker.prices, PortfolioTracker::default_prices());
+    }
+
+    #[test]
+    fn test_allocation_bars_equal_split() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 1.0, 45000.0);
+        tracker.record_purchase(CryptoCoin::Ethereum, 22.5, 2000.0);
+
+        let chart = tracker.render_allocation_bars(20);
+        let bars: Vec<usize> = chart.lines().map(|line| line.matches('#').count()).collect();
+        assert_eq!(bars, vec![10, 10]);
+        assert!(chart.contains("50.0%"));
+    }
+
+    #[test]
+    fn test_allocation_bars_empty_portfolio() {
+        let tracker = PortfolioTracker::new();
+        assert_eq!(tracker.render_allocation_bars(20), "  No holdings with value to chart\n");
+    }
+
+    #[test]
+    fn test_crypto_coin_extra_aliases() {
+        assert_eq!(CryptoCoin::from_string("xbt"), Some(CryptoCoin::Bitcoin));
+        assert_eq!(CryptoCoin::from_string("ETHER"), Some(CryptoCoin::Ethereum));
+        assert_eq!(CryptoCoin::from_string("  sol "), Some(CryptoCoin::Solana));
+        assert_eq!(CryptoCoin::Bitcoin.aliases(), vec!["bitcoin", "btc", "xbt"]);
+        for coin in CryptoCoin::all() {
+            for alias in coin.aliases() {
+                assert_eq!(CryptoCoin::from_string(alias), Some(coin.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_value_modes() {
+        // 2.675 is a tie at two decimals despite its binary representation
+        assert_eq!(round_value(2.675, RoundingMode::HalfUp, 2), 2.68);
+        assert_eq!(round_value(2.675, RoundingMode::HalfEven, 2), 2.68);
+        assert_eq!(round_value(2.675, RoundingMode::Truncate, 2), 2.67);
+        // A tie next to an even digit is where HalfUp and HalfEven part ways
+        assert_eq!(round_value(2.665, RoundingMode::HalfUp, 2), 2.67);
+        assert_eq!(round_value(2.665, RoundingMode::HalfEven, 2), 2.66);
+        assert_eq!(round_value(-2.665, RoundingMode::HalfUp, 2), -2.67);
+        assert_eq!(round_value(2.679, RoundingMode::Truncate, 2), 2.67);
+    }
+
+    #[test]
+    fn test_format_total_uses_rounding_mode() {
+        let mut tracker = PortfolioTracker::new();
+        assert_eq!(tracker.format_total(1234.665), "$1,234.67");
+        tracker.rounding_mode = RoundingMode::HalfEven;
+        assert_eq!(tracker.format_total(1234.665), "$1,234.66");
+        assert_eq!(RoundingMode::from_string(" Truncate "), Some(RoundingMode::Truncate));
+    }
+
+    #[test]
+    fn test_export_import_json_round_trip() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 0.5, 45000.0);
+        tracker.record_purchase(CryptoCoin::Solana, -3.0, 157.0);
+        tracker.update_price(CryptoCoin::Solana, 120.0);
+        let json = tracker.export_json();
+        assert!(json.contains("\"version\": 1"));
+
+        let mut restored = PortfolioTracker::new();
+        restored.record_purchase(CryptoCoin::Aptos, 10.0, 4.8);
+        restored.import_json(&json).unwrap();
+        assert_eq!(restored.portfolio, tracker.portfolio);
+        assert_eq!(restored.prices, tracker.prices);
+    }
+
+    #[test]
+    fn test_import_json_rejects_unknown_version() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 1.0, 45000.0);
+
+        let json = r#"{ "version": 99, "holdings": { "ETH": 2.0 }, "prices": {} }"#;
+        assert_eq!(tracker.import_json(json), Err("Unsupported export version 99".to_string()));
+        assert_eq!(tracker.portfolio[&CryptoCoin::Bitcoin], 1.0);
+        assert!(!tracker.portfolio.contains_key(&CryptoCoin::Ethereum));
+    }
+
+    #[test]
+    fn test_diff_added_removed_and_changed() {
+        let mut old = PortfolioTracker::new();
+        old.record_purchase(CryptoCoin::Bitcoin, 1.0, 45000.0);
+        old.record_purchase(CryptoCoin::Solana, 10.0, 157.0);
+        old.record_purchase(CryptoCoin::Cardano, 100.0, 0.45);
+
+        let mut new = PortfolioTracker::new();
+        new.record_purchase(CryptoCoin::Bitcoin, 1.0, 45000.0);
+        new.record_purchase(CryptoCoin::Solana, 4.0, 157.0);
+        new.record_purchase(CryptoCoin::Ethereum, 2.0, 3000.0);
+
+        assert_eq!(old.diff(&new), vec![
+            (CryptoCoin::Ethereum, 0.0, 2.0),
+            (CryptoCoin::Solana, 10.0, 4.0),
+            (CryptoCoin::Cardano, 100.0, 0.0),
+        ]);
+        assert!(new.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn test_biggest_movers() {
+        let mut tracker = PortfolioTracker::new();
+        assert_eq!(tracker.biggest_movers(), (None, None));
+
+        tracker.record_purchase(CryptoCoin::Bitcoin, 1.0, 45000.0);
+        tracker.record_purchase(CryptoCoin::Cardano, 100.0, 0.45);
+        tracker.record_purchase(CryptoCoin::Aptos, 10.0, 4.8);
+        tracker.changes_24h.insert(CryptoCoin::Bitcoin, 2.5);
+        tracker.changes_24h.insert(CryptoCoin::Cardano, -8.0);
+        tracker.changes_24h.insert(CryptoCoin::Aptos, 12.0);
+        // Not held, so its bigger move is ignored
+        tracker.changes_24h.insert(CryptoCoin::Solana, 40.0);
+
+        assert_eq!(tracker.biggest_movers(), (Some(CryptoCoin::Aptos), Some(CryptoCoin::Cardano)));
+    }
+
+    #[test]
+    fn test_market_cap_ranking() {
+        let mut tracker = PortfolioTracker::new();
+        assert_eq!(tracker.market_cap(&CryptoCoin::Bitcoin), Some(45000.0 * 19_700_000.0));
+
+        tracker.prices = HashMap::from([
+            (CryptoCoin::Bitcoin, 100.0),
+            (CryptoCoin::Ethereum, 10.0),
+            (CryptoCoin::Solana, 1.0),
+            (CryptoCoin::Aptos, 2.0),
+        ]);
+        tracker.supply = HashMap::from([
+            (CryptoCoin::Bitcoin, 10.0),
+            (CryptoCoin::Ethereum, 500.0),
+            (CryptoCoin::Solana, 300.0),
+            (CryptoCoin::Aptos, 100.0),
+            (CryptoCoin::Cardano, 1e12),
+        ]);
+
+        // Cardano has no price and Polkadot neither price nor supply, so both are unranked
+        let ranking: Vec<CryptoCoin> = tracker.market_cap_ranking().into_iter().map(|(coin, _)| coin).collect();
+        assert_eq!(ranking, vec![CryptoCoin::Ethereum, CryptoCoin::Bitcoin, CryptoCoin::Solana, CryptoCoin::Aptos]);
+        assert_eq!(tracker.market_cap(&CryptoCoin::Cardano), None);
+    }
+
+    #[test]
+    fn test_total_value_skips_missing_price() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 0.5, 45000.0);
+        tracker.record_purchase(CryptoCoin::Solana, 10.0, 157.0);
+        assert!(tracker.missing_prices().is_empty());
+
+        tracker.prices.remove(&CryptoCoin::Solana);
+        assert_eq!(tracker.total_value(), 22500.0);
+        assert_eq!(tracker.missing_prices(), vec![CryptoCoin::Solana]);
+    }
+
+    #[test]
+    fn test_fiat_to_amount() {
+        let mut tracker = PortfolioTracker::new();
+        assert_eq!(tracker.fiat_to_amount(&CryptoCoin::Bitcoin, 900.0), Some(0.02));
+
+        tracker.prices.remove(&CryptoCoin::Bitcoin);
+        assert_eq!(tracker.fiat_to_amount(&CryptoCoin::Bitcoin, 900.0), None);
+    }
+
+    #[test]
+    fn test_dust_below_threshold() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 1.0, 45000.0);
+        tracker.record_purchase(CryptoCoin::Cardano, 0.01 / 0.45, 0.45);
+
+        let dust = tracker.dust(1.0);
+        assert_eq!(dust.len(), 1);
+        assert_eq!(dust[0].0, CryptoCoin::Cardano);
+        assert!((dust[0].1 - 0.01).abs() < 1e-9);
+        assert!(tracker.dust(0.0).is_empty());
+    }
+
     #[test]
     fn test_menu_choice_from_input() {
         assert!(matches!(MenuChoice::from_input("1"), MenuChoice::ViewPortfolio));
         assert!(matches!(MenuChoice::from_input("invalid"), MenuChoice::Invalid(_)));
     }
+
+    #[test]
+    fn test_import_csv() {
+        let path = std::env::temp_dir().join(format!("portfolio_import_{}.csv", std::process::id()));
+        std::fs::write(&path, "symbol,amount\nBTC,0.5\neth, 2\nnotacoin,1\nSOL,-3\nbroken line\nbtc,0.25\n").unwrap();
+
+        let mut tracker = PortfolioTracker::new();
+        let imported = tracker.import_csv(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(imported, 3);
+        assert_eq!(tracker.portfolio.len(), 2);
+        assert_eq!(tracker.portfolio[&CryptoCoin::Bitcoin], 0.75);
+        assert_eq!(tracker.portfolio[&CryptoCoin::Ethereum], 2.0);
+        assert!(tracker.import_csv("/nonexistent/holdings.csv").is_err());
+    }
+
+    #[test]
+    fn test_simulate_with_override() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 1.0, 45000.0);
+        tracker.record_purchase(CryptoCoin::Solana, 10.0, 157.0);
+
+        assert_eq!(tracker.simulate(&HashMap::new()), 46570.0);
+        let overrides = HashMap::from([(CryptoCoin::Bitcoin, 60000.0)]);
+        assert_eq!(tracker.simulate(&overrides), 61570.0);
+        assert_eq!(tracker.prices[&CryptoCoin::Bitcoin], 45000.0);
+    }
+
+    #[test]
+    fn test_custom_display_precision() {
+        let mut tracker = PortfolioTracker::new();
+        assert_eq!(tracker.format_amount(1.5), "1.5000");
+        assert_eq!(tracker.format_price(0.45), "$0.45");
+
+        tracker.set_decimals_amount(0);
+        tracker.set_decimals_price(4);
+        assert_eq!(tracker.format_amount(2.0), "2");
+        assert_eq!(tracker.format_price(0.4512), "$0.4512");
+        assert_eq!(tracker.format_price(-90000.0), "-$90,000.0000");
+    }
+
+    #[test]
+    fn test_coins_not_held() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 1.0, 45000.0);
+        tracker.record_purchase(CryptoCoin::Solana, 5.0, 157.0);
+
+        assert_eq!(tracker.coins_not_held(), vec![
+            CryptoCoin::Aptos,
+            CryptoCoin::Cardano,
+            CryptoCoin::Ethereum,
+            CryptoCoin::Polkadot,
+        ]);
+    }
+
+    #[test]
+    fn test_break_even_after_two_buys() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Ethereum, 1.0, 1800.0);
+        tracker.record_purchase(CryptoCoin::Ethereum, 3.0, 2200.0);
+        tracker.record_purchase(CryptoCoin::Solana, 10.0, 150.0);
+
+        assert_eq!(tracker.break_even(&CryptoCoin::Ethereum), Some(2100.0));
+        assert_eq!(tracker.total_invested(), 8400.0 + 1500.0);
+        assert_eq!(tracker.break_even(&CryptoCoin::Bitcoin), None);
+
+        tracker.portfolio.insert(CryptoCoin::Cardano, 0.0);
+        assert_eq!(tracker.break_even(&CryptoCoin::Cardano), None);
+    }
+
+    #[test]
+    fn test_confirm_parsing() {
+        assert!(confirm_with("Proceed?", || "y".to_string()));
+        assert!(confirm_with("Proceed?", || " YES ".to_string()));
+        assert!(!confirm_with("Proceed?", || "n".to_string()));
+        assert!(!confirm_with("Proceed?", || "".to_string()));
+        assert!(!confirm_with("Proceed?", || "maybe".to_string()));
+    }
+
+    #[test]
+    fn test_remove_coin() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Aptos, 12.0, 4.8);
+        assert_eq!(tracker.remove_coin(&CryptoCoin::Aptos), Some(12.0));
+        assert!(tracker.portfolio.is_empty());
+        assert!(tracker.cost_basis.is_empty());
+        assert_eq!(tracker.remove_coin(&CryptoCoin::Aptos), None);
+    }
+
+    #[test]
+    fn test_short_position_reduces_total() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Ethereum, 50.0, 2000.0);
+        let long_only = tracker.simulate(&HashMap::new());
+
+        tracker.record_purchase(CryptoCoin::Bitcoin, -2.0, 45000.0);
+        assert_eq!(tracker.portfolio[&CryptoCoin::Bitcoin], -2.0);
+        assert_eq!(tracker.simulate(&HashMap::new()), long_only - 90000.0);
+        assert_eq!(tracker.break_even(&CryptoCoin::Bitcoin), Some(45000.0));
+    }
+
+    #[test]
+    fn test_partial_close_keeps_average_cost() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 2.0, 40000.0);
+        tracker.record_purchase(CryptoCoin::Bitcoin, -1.0, 50000.0);
+        assert_eq!(tracker.portfolio[&CryptoCoin::Bitcoin], 1.0);
+        assert_eq!(tracker.cost_basis[&CryptoCoin::Bitcoin], 40000.0);
+        assert_eq!(tracker.realized_pnl, 10000.0);
+
+        // Buying back part of a short realizes the drop in price as a gain
+        tracker.record_purchase(CryptoCoin::Solana, -4.0, 200.0);
+        tracker.record_purchase(CryptoCoin::Solana, 1.0, 150.0);
+        assert_eq!(tracker.portfolio[&CryptoCoin::Solana], -3.0);
+        assert_eq!(tracker.cost_basis[&CryptoCoin::Solana], 200.0);
+        assert_eq!(tracker.realized_pnl, 10050.0);
+    }
+
+    #[test]
+    fn test_trade_through_zero_flips_position() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 2.0, 40000.0);
+        tracker.record_purchase(CryptoCoin::Bitcoin, -3.0, 50000.0);
+        assert_eq!(tracker.portfolio[&CryptoCoin::Bitcoin], -1.0);
+        assert_eq!(tracker.cost_basis[&CryptoCoin::Bitcoin], 50000.0);
+        assert_eq!(tracker.realized_pnl, 20000.0);
+
+        tracker.record_purchase(CryptoCoin::Bitcoin, 1.0, 45000.0);
+        assert!(!tracker.portfolio.contains_key(&CryptoCoin::Bitcoin));
+        assert_eq!(tracker.realized_pnl, 25000.0);
+    }
+
+    #[test]
+    fn test_alert_triggers_above_threshold() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.alerts.push(Alert { coin: CryptoCoin::Bitcoin, above: Some(60000.0), below: None });
+        assert!(tracker.check_alerts().is_empty());
+
+        let triggered = tracker.update_price(CryptoCoin::Bitcoin, 61000.0);
+        assert_eq!(triggered.len(), 1);
+        assert!(triggered[0].contains("BTC") && triggered[0].contains("above"));
+    }
+
+    #[test]
+    fn test_alert_no_trigger() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.alerts.push(Alert { coin: CryptoCoin::Solana, above: Some(200.0), below: Some(100.0) });
+        assert!(tracker.update_price(CryptoCoin::Solana, 150.0).is_empty());
+        assert!(tracker.update_price(CryptoCoin::Bitcoin, 99.0).is_empty());
+    }
+
+    #[test]
+    fn test_read_input_line_handles_eof() {
+        let mut input = io::Cursor::new("  3 \n\n");
+        assert_eq!(read_input_line(&mut input), Some("3".to_string()));
+        assert_eq!(read_input_line(&mut input), Some(String::new()));
+        assert_eq!(read_input_line(&mut input), None);
+
+        let mut closed = io::Cursor::new("");
+        assert_eq!(read_input_line(&mut closed), None);
+    }
+
+    #[test]
+    fn test_run_command_add_and_total() {
+        let mut tracker = PortfolioTracker::new();
+        assert_eq!(tracker.run_command("add BTC 0.5"), "Added 0.5000 BTC (now 0.5000)");
+        assert_eq!(tracker.run_command("add eth 2"), "Added 2.0000 ETH (now 2.0000)");
+        assert_eq!(tracker.portfolio[&CryptoCoin::Bitcoin], 0.5);
+        assert_eq!(tracker.run_command("total"), "Total Value: $26,500.00");
+
+        assert_eq!(tracker.run_command("price ETH 2500"), "ETH price set to $2,500.00");
+        assert_eq!(tracker.run_command("remove ETH"), "Removed 2.0000 ETH");
+        assert_eq!(tracker.run_command("total"), "Total Value: $22,500.00");
+    }
+
+    #[test]
+    fn test_run_command_errors() {
+        let mut tracker = PortfolioTracker::new();
+        assert_eq!(tracker.run_command("buy BTC 1"), "Error: unknown command 'buy BTC 1'");
+        assert_eq!(tracker.run_command("add DOGE 1"), "Error: unknown coin 'DOGE'");
+        assert_eq!(tracker.run_command("add BTC lots"), "Error: 'lots' is not a number");
+        assert!(tracker.portfolio.is_empty());
+    }
+
+    #[test]
+    fn test_run_command_rejects_non_finite_numbers() {
+        let mut tracker = PortfolioTracker::new();
+        assert_eq!(tracker.run_command("add BTC NaN"), "Error: 'NaN' is not a finite number");
+        assert_eq!(tracker.run_command("add BTC -inf"), "Error: '-inf' is not a finite number");
+        assert!(tracker.portfolio.is_empty());
+
+        assert_eq!(tracker.run_command("price SOL inf"), "Error: 'inf' is not a finite number");
+        assert_eq!(tracker.run_command("price SOL NaN"), "Error: 'NaN' is not a finite number");
+        assert_eq!(tracker.run_command("price SOL -5"), "Error: '-5' must be greater than zero");
+        assert_eq!(tracker.prices[&CryptoCoin::Solana], 157.0);
+
+        // Shorts still go through as signed amounts
+        assert_eq!(tracker.run_command("add BTC -0.5"), "Added -0.5000 BTC (now -0.5000)");
+    }
+
+    #[test]
+    fn test_total_in_currencies() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Ethereum, 5.0, 2000.0);
+        let rates = HashMap::from([
+            ("USD".to_string(), 1.0),
+            ("EUR".to_string(), 0.9),
+            ("BAD".to_string(), -1.0),
+        ]);
+
+        let totals = tracker.total_in_currencies(&rates);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals["USD"], 10000.0);
+        assert_eq!(totals["EUR"], 9000.0);
+    }
+
+    #[test]
+    fn test_parse_decimals() {
+        assert_eq!(parse_decimals(" \n"), Ok(None));
+        assert_eq!(parse_decimals("0"), Ok(Some(0)));
+        assert_eq!(parse_decimals(" 6 "), Ok(Some(6)));
+        assert!(parse_decimals("-1").is_err());
+        assert!(parse_decimals("NaN").is_err());
+        assert!(parse_decimals("13").is_err());
+    }
+
+    #[test]
+    fn test_format_usd() {
+        assert_eq!(format_usd(1234567.89), "$1,234,567.89");
+        assert_eq!(format_usd(0.45), "$0.45");
+        assert_eq!(format_usd(999.999), "$1,000.00");
+        assert_eq!(format_usd(-1234.5), "-$1,234.50");
+        assert_eq!(format_usd(-0.001), "$0.00");
+        assert_eq!(format_usd_with(45000.0, 0), "$45,000");
+    }
+
+    #[test]
+    fn test_sell_coin_partial_realized_gain() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Bitcoin, 2.0, 40000.0);
+        tracker.record_purchase(CryptoCoin::Bitcoin, 2.0, 50000.0);
+
+        let gain = tracker.sell_coin(CryptoCoin::Bitcoin, 1.0, 50000.0).unwrap();
+        assert_eq!(gain, 5000.0);
+        assert_eq!(tracker.realized_pnl, 5000.0);
+        assert_eq!(tracker.portfolio[&CryptoCoin::Bitcoin], 3.0);
+        assert_eq!(tracker.cost_basis[&CryptoCoin::Bitcoin], 45000.0);
+    }
+
+    #[test]
+    fn test_sell_coin_oversell_errors() {
+        let mut tracker = PortfolioTracker::new();
+        tracker.record_purchase(CryptoCoin::Ethereum, 1.0, 2000.0);
+
+        assert!(tracker.sell_coin(CryptoCoin::Ethereum, 1.5, 2500.0).is_err());
+        assert!(tracker.sell_coin(CryptoCoin::Solana, 1.0, 100.0).is_err());
+        assert_eq!(tracker.portfolio[&CryptoCoin::Ethereum], 1.0);
+        assert_eq!(tracker.realized_pnl, 0.0);
+    }
 }
\ No newline at end of file
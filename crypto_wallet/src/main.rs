@@ -1,82 +1,650 @@
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[path = "../../shared/stats.rs"]
+#[allow(dead_code)]
+mod stats;
+use stats::{summarize, Summary};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TxKind {
+    Sent,
+    Received,
+    Deposit,
+    Interest,
+    Fee,
+}
+
+// Units an amount can be expressed in - balances are stored in wei
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Denomination {
+    Wei,
+    Gwei,
+    Ether,
+}
+
+pub const WEI_PER_ETHER: u128 = 1_000_000_000_000_000_000;
+
+// Length of the send rate-limit window (one minute)
+pub const SEND_WINDOW_NANOS: u128 = 60_000_000_000;
+
+// Bumped by every split so its part ids differ from any earlier split's
+static SPLIT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl Denomination {
+    pub fn wei_per_unit(self) -> u128 {
+        match self {
+            Denomination::Wei => 1,
+            Denomination::Gwei => 1_000_000_000,
+            Denomination::Ether => WEI_PER_ETHER,
+        }
+    }
+
+    // Convert an amount in this unit to wei, None on overflow
+    pub fn to_wei(self, amount: u128) -> Option<u128> {
+        amount.checked_mul(self.wei_per_unit())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transaction {
+    pub kind: TxKind,
+    pub amount: u128,
+    pub timestamp: u128,
+}
+
+impl Transaction {
+    // Record a transaction stamped with the current time (nanoseconds since epoch)
+    pub fn now(kind: TxKind, amount: u128) -> Transaction {
+        Transaction { kind, amount, timestamp: now_nanos() }
+    }
+}
+
+// Current time in nanoseconds since the Unix epoch
+pub fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos()
+}
+
+// Summary statistics over a set of wallets (balances in wei)
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletStats {
+    pub total: u128,
+    pub count: usize,
+    pub min: u128,
+    pub max: u128,
+    pub average: f64,
+}
+
+// Fee revenue collected from fee-charging sends and transfers (in wei)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Treasury {
+    pub collected: u128,
+}
+
+impl Treasury {
+    pub fn collect(&mut self, fee: u128) {
+        self.collected = self.collected.saturating_add(fee);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Wallet {
-    pub balance: u64,
+    pub balance: u128,
     pub id: String,
+    pub history: Vec<Transaction>,
+    pub min_balance: u128,
+    pub frozen: bool,
+    // Registries saved before owners existed load as unowned
+    #[serde(default)]
+    pub owner: String,
+    // (timestamp, balance in wei) snapshots recorded by snapshot_balance
+    #[serde(default)]
+    pub balance_history: Vec<(u128, u128)>,
+    // Sends allowed per SEND_WINDOW_NANOS - 0 means unlimited
+    #[serde(default)]
+    pub max_sends_per_window: u32,
+    #[serde(default)]
+    pub sends_this_window: u32,
+    #[serde(default)]
+    pub window_start: u128,
+    // Fee charged on top of each fee-charging send, in basis points of the amount
+    #[serde(default)]
+    pub send_fee_bps: u32,
 }
 
 impl Wallet {
     // 1. Create a new wallet with initial balance
-    pub fn new_wallet(balance: u64) -> Wallet {
+    pub fn new_wallet(balance: u128) -> Wallet {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
-        use std::time::{SystemTime, UNIX_EPOCH};
 
         // Generate a simple ID based on timestamp
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
+        let timestamp = now_nanos();
 
         let mut hasher = DefaultHasher::new();
         timestamp.hash(&mut hasher);
         let id = format!("wallet_{:x}", hasher.finish());
 
-        Wallet { balance, id }
+        Wallet::with_id(balance, id)
+    }
+
+    // 1b. Create a wallet whose id is derived from a seed string.
+    // The same seed always yields the same id (FNV-1a, stable across Rust versions),
+    // so seeds must be unique - two wallets created from one seed share an id.
+    pub fn new_wallet_with_seed(balance: u128, seed: &str) -> Wallet {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in seed.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        let id = format!("wallet_{:x}", hash);
+
+        Wallet::with_id(balance, id)
+    }
+
+    // Shared constructor once an id has been generated
+    fn with_id(balance: u128, id: String) -> Wallet {
+        Wallet {
+            balance,
+            id,
+            history: Vec::new(),
+            min_balance: 0,
+            frozen: false,
+            owner: String::new(),
+            balance_history: Vec::new(),
+            max_sends_per_window: 0,
+            sends_this_window: 0,
+            window_start: 0,
+            send_fee_bps: 0,
+        }
+    }
+
+    // Lock part of the balance as a reserve that sends cannot dip below
+    pub fn set_min_balance(wallet: &mut Wallet, reserve: u128) {
+        wallet.min_balance = reserve;
+    }
+
+    // Cap how many sends are allowed per window (0 removes the limit). Only the send_money
+    // family counts and is limited - transfers and payouts between wallets are not.
+    pub fn set_send_limit(wallet: &mut Wallet, max_sends: u32) {
+        wallet.max_sends_per_window = max_sends;
+    }
+
+    // Set the fee for send_money_with_fee / transfer_with_fee (0 makes them free)
+    pub fn set_send_fee(wallet: &mut Wallet, fee_bps: u32) {
+        wallet.send_fee_bps = fee_bps;
+    }
+
+    // Fee owed on sending `amount`: amount * send_fee_bps / 10000, rounded down
+    pub fn send_fee(wallet: &Wallet, amount: u128) -> Result<u128, String> {
+        amount
+            .checked_mul(wallet.send_fee_bps as u128)
+            .map(|v| v / 10_000)
+            .ok_or_else(|| "Fee calculation overflowed".to_string())
+    }
+
+    // Debit an already-checked fee and hand it to the treasury
+    fn charge_fee(wallet: &mut Wallet, fee: u128, treasury: &mut Treasury) {
+        if fee == 0 {
+            return;
+        }
+        wallet.balance -= fee;
+        wallet.history.push(Transaction::now(TxKind::Fee, fee));
+        treasury.collect(fee);
+    }
+
+    // Freeze a wallet - sends, transfers and deposits are rejected until unfrozen
+    pub fn freeze(wallet: &mut Wallet) {
+        wallet.frozen = true;
+    }
+
+    pub fn unfreeze(wallet: &mut Wallet) {
+        wallet.frozen = false;
+    }
+
+    fn check_not_frozen(wallet: &Wallet) -> Result<(), String> {
+        if wallet.frozen {
+            return Err("wallet is frozen".to_string());
+        }
+        Ok(())
+    }
+
+    // Reject a debit that would leave the wallet below its reserve
+    fn check_min_balance(wallet: &Wallet, amount: u128) -> Result<(), String> {
+        let remaining = wallet.balance - amount;
+        if remaining < wallet.min_balance {
+            return Err(format!("Transaction would breach minimum balance! Reserve: {} ETH, Remaining: {} ETH", Wallet::format_ether(wallet.min_balance), Wallet::format_ether(remaining)));
+        }
+        Ok(())
     }
 
     // 2. Check balance (immutable borrow)
-    pub fn check_balance(wallet: &Wallet) -> u64 {
+    pub fn check_balance(wallet: &Wallet) -> u128 {
         wallet.balance
     }
 
     // 3. Send money (mutable borrow) - returns Result for error handling
-    pub fn send_money(wallet: &mut Wallet, amount: u64) -> Result<(), String> {
+    pub fn send_money(wallet: &mut Wallet, amount: u128) -> Result<(), String> {
+        Wallet::send_money_at(wallet, amount, now_nanos())
+    }
+
+    // 3a. Dry run of send_money: the same checks, without touching the wallet
+    pub fn can_send(wallet: &Wallet, amount: u128) -> Result<(), String> {
+        Wallet::can_send_at(wallet, amount, now_nanos())
+    }
+
+    // can_send at a given time, including the rate limit send_money_at enforces.
+    // A window that has elapsed by `now` counts as empty.
+    pub fn can_send_at(wallet: &Wallet, amount: u128, now: u128) -> Result<(), String> {
+        let window_elapsed = now.saturating_sub(wallet.window_start) >= SEND_WINDOW_NANOS;
+        if wallet.max_sends_per_window > 0 && !window_elapsed && wallet.sends_this_window >= wallet.max_sends_per_window {
+            return Err("rate limit exceeded".to_string());
+        }
+        Wallet::check_can_debit(wallet, amount)
+    }
+
+    // The frozen, balance and reserve checks every debit needs, without the send rate limit
+    fn check_can_debit(wallet: &Wallet, amount: u128) -> Result<(), String> {
+        Wallet::check_not_frozen(wallet)?;
         if wallet.balance < amount {
-            return Err(format!("Insufficient balance! Have: {}, Need: {}", wallet.balance, amount));
+            return Err(format!("Insufficient balance! Have: {} ETH, Need: {} ETH", Wallet::format_ether(wallet.balance), Wallet::format_ether(amount)));
+        }
+        Wallet::check_min_balance(wallet, amount)
+    }
+
+    // 3b. Send money only if the caller owns the wallet - unowned wallets reject everyone
+    pub fn send_money_authorized(wallet: &mut Wallet, amount: u128, caller: &str) -> Result<(), String> {
+        if wallet.owner.is_empty() || caller != wallet.owner {
+            return Err("unauthorized".to_string());
+        }
+        Wallet::send_money(wallet, amount)
+    }
+
+    // 3c. send_money at a given time, so the rate limit can be tested.
+    // The counter resets once SEND_WINDOW_NANOS have passed, and rejected sends don't count.
+    pub fn send_money_at(wallet: &mut Wallet, amount: u128, now: u128) -> Result<(), String> {
+        Wallet::can_send_at(wallet, amount, now)?;
+        if now.saturating_sub(wallet.window_start) >= SEND_WINDOW_NANOS {
+            wallet.window_start = now;
+            wallet.sends_this_window = 0;
         }
         wallet.balance -= amount;
+        wallet.sends_this_window += 1;
+        wallet.history.push(Transaction::now(TxKind::Sent, amount));
         Ok(())
     }
 
+    // 3d. send_money plus the wallet's fee, credited to `treasury`. The balance must
+    // cover amount + fee, otherwise nothing is sent or charged.
+    pub fn send_money_with_fee(wallet: &mut Wallet, amount: u128, treasury: &mut Treasury) -> Result<u128, String> {
+        let fee = Wallet::send_fee(wallet, amount)?;
+        let total = amount.checked_add(fee).ok_or("Send amount plus fee overflowed")?;
+        Wallet::can_send(wallet, total)?;
+        Wallet::send_money(wallet, amount)?;
+        Wallet::charge_fee(wallet, fee, treasury);
+        Ok(fee)
+    }
+
+    // Set the owner allowed to authorize sends
+    pub fn assign_owner(wallet: &mut Wallet, owner: &str) {
+        wallet.owner = owner.to_string();
+    }
+
     // 4. Transfer ownership (moves the wallet)
     pub fn transfer_ownership(wallet: Wallet) -> Wallet {
         wallet // This moves ownership
     }
 
     // 5. Transfer between wallets
-    pub fn transfer_between(from: &mut Wallet, to: &mut Wallet, amount: u64) -> Result<(), String> {
+    pub fn transfer_between(from: &mut Wallet, to: &mut Wallet, amount: u128) -> Result<(), String> {
+        Wallet::check_not_frozen(from)?;
+        Wallet::check_not_frozen(to)?;
         if from.balance < amount {
-            return Err(format!("Insufficient balance in source wallet! Have: {}, Need: {}", from.balance, amount));
+            return Err(format!("Insufficient balance in source wallet! Have: {} ETH, Need: {} ETH", Wallet::format_ether(from.balance), Wallet::format_ether(amount)));
         }
+        Wallet::check_min_balance(from, amount)?;
+        let credited = to
+            .balance
+            .checked_add(amount)
+            .ok_or_else(|| format!("Transfer overflow! Recipient has: {} ETH, Adding: {} ETH", Wallet::format_ether(to.balance), Wallet::format_ether(amount)))?;
         from.balance -= amount;
-        to.balance += amount;
+        to.balance = credited;
+        from.history.push(Transaction::now(TxKind::Sent, amount));
+        to.history.push(Transaction::now(TxKind::Received, amount));
         Ok(())
     }
 
+    // 5a. transfer_between where the sender also pays its send fee into `treasury`
+    pub fn transfer_with_fee(from: &mut Wallet, to: &mut Wallet, amount: u128, treasury: &mut Treasury) -> Result<u128, String> {
+        let fee = Wallet::send_fee(from, amount)?;
+        let total = amount.checked_add(fee).ok_or("Transfer amount plus fee overflowed")?;
+        Wallet::check_not_frozen(to)?;
+        Wallet::check_can_debit(from, total)?;
+        Wallet::transfer_between(from, to, amount)?;
+        Wallet::charge_fee(from, fee, treasury);
+        Ok(fee)
+    }
+
+    // 5b. Pay several wallets from one source - all credits apply or none do
+    pub fn payout(from: &mut Wallet, recipients: &mut [(&mut Wallet, u128)]) -> Result<(), String> {
+        Wallet::check_not_frozen(from)?;
+        for (to, _) in recipients.iter() {
+            Wallet::check_not_frozen(to)?;
+        }
+        let total = recipients
+            .iter()
+            .try_fold(0u128, |acc, (_, amount)| acc.checked_add(*amount))
+            .ok_or("Payout total overflows u128")?;
+        if from.balance < total {
+            return Err(format!("Insufficient balance for payout! Have: {} ETH, Need: {} ETH", Wallet::format_ether(from.balance), Wallet::format_ether(total)));
+        }
+        Wallet::check_min_balance(from, total)?;
+
+        from.balance -= total;
+        for i in 0..recipients.len() {
+            let (to, amount) = &mut recipients[i];
+            match to.balance.checked_add(*amount) {
+                Some(new_balance) => to.balance = new_balance,
+                None => {
+                    let failed_id = to.id.clone();
+                    // Roll back the credits already applied and the source debit
+                    for (prev, prev_amount) in recipients[..i].iter_mut() {
+                        prev.balance -= *prev_amount;
+                    }
+                    from.balance += total;
+                    return Err(format!("Payout to {} would overflow its balance; payout rolled back", failed_id));
+                }
+            }
+        }
+
+        for (to, amount) in recipients.iter_mut() {
+            from.history.push(Transaction::now(TxKind::Sent, *amount));
+            to.history.push(Transaction::now(TxKind::Received, *amount));
+        }
+        Ok(())
+    }
+
+    // 5c. Divide everything above the reserve into `parts` new wallets, leaving the source
+    // at its min_balance. Each part gets spendable / parts; the integer remainder goes to
+    // the first so nothing is lost. Every call gets fresh ids, so the same wallet can be
+    // split again and all the parts registered.
+    pub fn split(wallet: &mut Wallet, parts: usize) -> Result<Vec<Wallet>, String> {
+        if parts == 0 {
+            return Err("Cannot split a wallet into 0 parts".to_string());
+        }
+        Wallet::check_not_frozen(wallet)?;
+
+        let spendable = wallet.balance.saturating_sub(wallet.min_balance);
+        let share = spendable / parts as u128;
+        let remainder = spendable % parts as u128;
+        let batch = format!("{}/split/{}-{}", wallet.id, now_nanos(), SPLIT_COUNTER.fetch_add(1, Ordering::Relaxed));
+        let wallets: Vec<Wallet> = (0..parts)
+            .map(|i| {
+                let amount = if i == 0 { share + remainder } else { share };
+                let mut part = Wallet::new_wallet_with_seed(amount, &format!("{}/{}", batch, i));
+                if amount > 0 {
+                    part.history.push(Transaction::now(TxKind::Received, amount));
+                }
+                part
+            })
+            .collect();
+
+        if spendable > 0 {
+            wallet.history.push(Transaction::now(TxKind::Sent, spendable));
+        }
+        wallet.balance -= spendable;
+        Ok(wallets)
+    }
+
+    // 5d. Combine several wallets into a new one with a fresh id.
+    // Balances are summed with overflow checks and histories merged in timestamp order.
+    pub fn merge(wallets: Vec<Wallet>) -> Result<Wallet, String> {
+        if wallets.is_empty() {
+            return Err("Cannot merge an empty list of wallets".to_string());
+        }
+        for wallet in &wallets {
+            Wallet::check_not_frozen(wallet)?;
+        }
+        let total = wallets
+            .iter()
+            .try_fold(0u128, |acc, w| acc.checked_add(w.balance))
+            .ok_or("Merged balance overflows u128")?;
+
+        let mut merged = Wallet::new_wallet(total);
+        merged.history = wallets.into_iter().flat_map(|w| w.history).collect();
+        merged.history.sort_by_key(|tx| tx.timestamp);
+        Ok(merged)
+    }
+
     // 6. Get wallet info
     pub fn get_wallet_info(wallet: &Wallet) -> String {
-        format!("Wallet ID: {}, Balance: {} ETH", wallet.id, wallet.balance)
+        format!("Wallet ID: {}, Balance: {} ETH", wallet.id, Wallet::format_ether(wallet.balance))
+    }
+
+    // Format a wei amount as ether, keeping only the significant decimal places
+    pub fn format_ether(wei: u128) -> String {
+        let whole = wei / WEI_PER_ETHER;
+        let fraction = wei % WEI_PER_ETHER;
+        if fraction == 0 {
+            return whole.to_string();
+        }
+        let digits = format!("{:018}", fraction);
+        format!("{}.{}", whole, digits.trim_end_matches('0'))
+    }
+
+    // Balance expressed in the given unit (truncated towards zero)
+    pub fn balance_in(wallet: &Wallet, unit: Denomination) -> u128 {
+        wallet.balance / unit.wei_per_unit()
+    }
+
+    // Send an amount given in any unit - converted to wei before sending
+    pub fn send_in(wallet: &mut Wallet, amount: u128, unit: Denomination) -> Result<(), String> {
+        let wei = unit
+            .to_wei(amount)
+            .ok_or_else(|| format!("Amount {} {:?} overflows when converted to wei", amount, unit))?;
+        Wallet::send_money(wallet, wei)
     }
 
     // 7. Calculate the total balance of multiple wallets
-    pub fn batch_check(wallets: &[Wallet]) -> u64 {
+    pub fn batch_check(wallets: &[Wallet]) -> u128 {
         wallets.iter().map(|w| w.balance).sum()
     }
 
+    // 7b. Total, count, min, max and average balance in a single pass.
+    // Errors if the total overflows u128, like merge.
+    pub fn batch_stats(wallets: &[Wallet]) -> Result<WalletStats, String> {
+        if wallets.is_empty() {
+            return Ok(WalletStats { total: 0, count: 0, min: 0, max: 0, average: 0.0 });
+        }
+        let mut stats = WalletStats { total: 0, count: 0, min: u128::MAX, max: 0, average: 0.0 };
+        for wallet in wallets {
+            stats.total = stats.total.checked_add(wallet.balance).ok_or("Total balance overflows u128")?;
+            stats.count += 1;
+            stats.min = stats.min.min(wallet.balance);
+            stats.max = stats.max.max(wallet.balance);
+        }
+        stats.average = stats.total as f64 / stats.count as f64;
+        Ok(stats)
+    }
+
+    // 7c. Balance statistics in ETH via the shared summarize helper. Converting to
+    // f64 loses wei precision, so batch_check stays the exact total.
+    pub fn balance_summary(wallets: &[Wallet]) -> Summary {
+        summarize(wallets.iter().map(|w| w.balance as f64 / WEI_PER_ETHER as f64))
+    }
+
     // 8. Clone wallet (create backup)
     pub fn clone_wallet(wallet: &Wallet) -> Wallet {
         wallet.clone()
     }
+
+    // 9. Deposit funds into a wallet
+    pub fn deposit(wallet: &mut Wallet, amount: u128) -> Result<(), String> {
+        Wallet::check_not_frozen(wallet)?;
+        wallet.balance = wallet
+            .balance
+            .checked_add(amount)
+            .ok_or_else(|| format!("Deposit overflow! Have: {} ETH, Adding: {} ETH", Wallet::format_ether(wallet.balance), Wallet::format_ether(amount)))?;
+        wallet.history.push(Transaction::now(TxKind::Deposit, amount));
+        Ok(())
+    }
+
+    // 9b. Credit simple interest: balance * rate_bps / 10000 * days / 365, rounded down.
+    // Returns the interest credited.
+    pub fn accrue_interest(wallet: &mut Wallet, annual_rate_bps: u32, days: u32) -> Result<u128, String> {
+        Wallet::check_not_frozen(wallet)?;
+        let interest = wallet
+            .balance
+            .checked_mul(annual_rate_bps as u128)
+            .and_then(|v| v.checked_mul(days as u128))
+            .map(|v| v / (10_000 * 365))
+            .ok_or("Interest calculation overflowed")?;
+        wallet.balance = wallet
+            .balance
+            .checked_add(interest)
+            .ok_or_else(|| format!("Interest overflow! Have: {} ETH, Adding: {} ETH", Wallet::format_ether(wallet.balance), Wallet::format_ether(interest)))?;
+        if interest > 0 {
+            wallet.history.push(Transaction::now(TxKind::Interest, interest));
+        }
+        Ok(interest)
+    }
+
+    // 9c. Compound interest: each of `periods` years adds balance * rate, so interest
+    // earns interest. Integer math in wei, rounded down every period. Returns the total earned.
+    pub fn accrue_compound(wallet: &mut Wallet, annual_rate_bps: u32, periods: u32) -> Result<u128, String> {
+        Wallet::check_not_frozen(wallet)?;
+        let mut balance = wallet.balance;
+        for _ in 0..periods {
+            let interest = balance
+                .checked_mul(annual_rate_bps as u128)
+                .map(|v| v / 10_000)
+                .ok_or("Interest calculation overflowed")?;
+            balance = balance
+                .checked_add(interest)
+                .ok_or_else(|| format!("Interest overflow! Have: {} ETH, Adding: {} ETH", Wallet::format_ether(balance), Wallet::format_ether(interest)))?;
+        }
+
+        let earned = balance - wallet.balance;
+        wallet.balance = balance;
+        if earned > 0 {
+            wallet.history.push(Transaction::now(TxKind::Interest, earned));
+        }
+        Ok(earned)
+    }
+
+    // 9d. Record the current balance at `timestamp` (nanoseconds since epoch)
+    pub fn snapshot_balance(wallet: &mut Wallet, timestamp: u128) {
+        wallet.balance_history.push((timestamp, wallet.balance));
+    }
+
+    // 9e. Signed change in wei from the earliest to the latest snapshot - 0 with fewer than two
+    pub fn balance_change(wallet: &Wallet) -> i128 {
+        let (Some(&(_, earliest)), Some(&(_, latest))) = (wallet.balance_history.first(), wallet.balance_history.last()) else {
+            return 0;
+        };
+        if latest >= earliest {
+            i128::try_from(latest - earliest).unwrap_or(i128::MAX)
+        } else {
+            -i128::try_from(earliest - latest).unwrap_or(i128::MAX)
+        }
+    }
+
+    // 10. Format the transaction history as a statement
+    pub fn statement(wallet: &Wallet) -> String {
+        let mut out = format!("Statement for {}\n", wallet.id);
+        if wallet.history.is_empty() {
+            out.push_str("  No transactions\n");
+        }
+        for (i, tx) in wallet.history.iter().enumerate() {
+            out.push_str(&format!("  {:>3}. {:<9} {:>10} ETH  @ {}\n", i + 1, format!("{:?}", tx.kind), Wallet::format_ether(tx.amount), tx.timestamp));
+        }
+        out.push_str(&format!("  Closing balance: {} ETH", Wallet::format_ether(wallet.balance)));
+        out
+    }
+}
+
+// Registry of wallets keyed by their generated id
+#[derive(Debug, Default)]
+pub struct WalletRegistry {
+    pub wallets: HashMap<String, Wallet>,
+}
+
+impl WalletRegistry {
+    pub fn new() -> WalletRegistry {
+        WalletRegistry { wallets: HashMap::new() }
+    }
+
+    // Add a wallet - ids must be unique within the registry
+    pub fn register(&mut self, wallet: Wallet) -> Result<(), String> {
+        if self.wallets.contains_key(&wallet.id) {
+            return Err(format!("Wallet {} is already registered", wallet.id));
+        }
+        self.wallets.insert(wallet.id.clone(), wallet);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Wallet> {
+        self.wallets.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Wallet> {
+        self.wallets.get_mut(id)
+    }
+
+    // Look up both wallets by id and perform a checked transfer
+    pub fn transfer(&mut self, from_id: &str, to_id: &str, amount: u128) -> Result<(), String> {
+        if from_id == to_id {
+            return Err(format!("Cannot transfer from wallet {} to itself", from_id));
+        }
+        match self.wallets.get_disjoint_mut([from_id, to_id]) {
+            [Some(from), Some(to)] => Wallet::transfer_between(from, to, amount),
+            [None, _] => Err(format!("Source wallet {} not found", from_id)),
+            [_, None] => Err(format!("Destination wallet {} not found", to_id)),
+        }
+    }
+
+    // Write all wallets (with their history) to a JSON file, sorted by id
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut wallets: Vec<&Wallet> = self.wallets.values().collect();
+        wallets.sort_by(|a, b| a.id.cmp(&b.id));
+        let json = serde_json::to_string_pretty(&wallets).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    // Read a registry back from disk - a missing file yields an empty registry
+    pub fn load(path: &str) -> io::Result<WalletRegistry> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(WalletRegistry::new()),
+            Err(e) => return Err(e),
+        };
+        let wallets: Vec<Wallet> = serde_json::from_str(&json).map_err(io::Error::other)?;
+        let mut registry = WalletRegistry::new();
+        for wallet in wallets {
+            registry.register(wallet).map_err(io::Error::other)?;
+        }
+        Ok(registry)
+    }
 }
 
 fn main() {
     println!("Crypto Wallet System Demo\n");
 
+    // Amounts are stored in wei - this converts whole ether for the demo
+    let eth = |amount: u128| Denomination::Ether.to_wei(amount).unwrap();
+
     // Task 1: Create wallets with some ETH
     println!("1. Creating wallets...");
-    let mut wallet1 = Wallet::new_wallet(100);
-    let mut wallet2 = Wallet::new_wallet(50);
-    let wallet3 = Wallet::new_wallet(75);
+    let mut wallet1 = Wallet::new_wallet(eth(100));
+    let mut wallet2 = Wallet::new_wallet(eth(50));
+    let wallet3 = Wallet::new_wallet(eth(75));
 
     println!("   {}", Wallet::get_wallet_info(&wallet1));
     println!("   {}", Wallet::get_wallet_info(&wallet2));
@@ -84,39 +652,54 @@ fn main() {
 
     // Task 2: Check balance without losing ownership
     println!("\n2. Checking balances (immutable borrow)...");
-    println!("   Wallet1 balance: {} ETH", Wallet::check_balance(&wallet1));
-    println!("   Wallet2 balance: {} ETH", Wallet::check_balance(&wallet2));
+    println!("   Wallet1 balance: {} ETH", Wallet::format_ether(Wallet::check_balance(&wallet1)));
+    println!("   Wallet2 balance: {} ETH", Wallet::format_ether(Wallet::check_balance(&wallet2)));
     // wallet1 is still usable here because we only borrowed it!
 
     // Task 3: Send some ETH using mutable borrow
     println!("\n3. Sending ETH from wallet1...");
-    match Wallet::send_money(&mut wallet1, 30) {
+    if let Err(e) = Wallet::can_send(&wallet1, eth(500)) {
+        println!("   Dry run for 500 ETH would fail: {}", e);
+    }
+    match Wallet::send_money(&mut wallet1, eth(30)) {
         Ok(()) => println!(" Successfully sent 30 ETH"),
         Err(e) => println!("  Error: {}", e),
     }
-    println!("   Wallet1 new balance: {} ETH", Wallet::check_balance(&wallet1));
+    println!("   Wallet1 new balance: {} ETH", Wallet::format_ether(Wallet::check_balance(&wallet1)));
 
     // Task 4: Transfer money between two wallets
     println!("\n4. Transferring between wallets...");
     println!("   Before transfer:");
-    println!("     Wallet1: {} ETH", Wallet::check_balance(&wallet1));
-    println!("     Wallet2: {} ETH", Wallet::check_balance(&wallet2));
+    println!("     Wallet1: {} ETH", Wallet::format_ether(Wallet::check_balance(&wallet1)));
+    println!("     Wallet2: {} ETH", Wallet::format_ether(Wallet::check_balance(&wallet2)));
 
-    match Wallet::transfer_between(&mut wallet1, &mut wallet2, 20) {
+    match Wallet::transfer_between(&mut wallet1, &mut wallet2, eth(20)) {
         Ok(()) => println!("  Successfully transferred 20 ETH from wallet1 to wallet2"),
         Err(e) => println!("  Error: {}", e),
     }
 
     println!("   After transfer:");
-    println!("     Wallet1: {} ETH", Wallet::check_balance(&wallet1));
-    println!("     Wallet2: {} ETH", Wallet::check_balance(&wallet2));
+    println!("     Wallet1: {} ETH", Wallet::format_ether(Wallet::check_balance(&wallet1)));
+    println!("     Wallet2: {} ETH", Wallet::format_ether(Wallet::check_balance(&wallet2)));
 
     // Task 5: Calculate total balance of 3+ wallets
     println!("\n5. Calculating total balance of all wallets...");
     let wallets = [&wallet1, &wallet2, &wallet3];
     let wallet_refs: Vec<Wallet> = wallets.iter().map(|&w| w.clone()).collect();
     let total = Wallet::batch_check(&wallet_refs);
-    println!("   Total balance across all wallets: {} ETH", total);
+    println!("   Total balance across all wallets: {} ETH", Wallet::format_ether(total));
+    match Wallet::batch_stats(&wallet_refs) {
+        Ok(stats) => println!(
+            "   {} wallets - min: {} ETH, max: {} ETH, average: {:.2} ETH",
+            stats.count,
+            Wallet::format_ether(stats.min),
+            Wallet::format_ether(stats.max),
+            stats.average / WEI_PER_ETHER as f64
+        ),
+        Err(e) => println!("   Error: {}", e),
+    }
+    let summary = Wallet::balance_summary(&wallet_refs);
+    println!("   Spread between largest and smallest: {:.2} ETH", summary.max - summary.min);
 
     // Task 6: Transfer wallet ownership
     println!("\n6. Transferring wallet ownership...");
@@ -132,8 +715,8 @@ fn main() {
 
     // Task 8: Handle insufficient balance
     println!("\n8. Testing insufficient balance handling...");
-    println!("   Attempting to send 1000 ETH from wallet1 (balance: {} ETH)...", Wallet::check_balance(&wallet1));
-    match Wallet::send_money(&mut wallet1, 1000) {
+    println!("   Attempting to send 1000 ETH from wallet1 (balance: {} ETH)...", Wallet::format_ether(Wallet::check_balance(&wallet1)));
+    match Wallet::send_money(&mut wallet1, eth(1000)) {
         Ok(()) => println!("Transaction successful"),
         Err(e) => println!("Transaction failed: {}", e),
     }
@@ -144,6 +727,146 @@ fn main() {
     println!("   Original: {}", Wallet::get_wallet_info(&wallet1));
     println!("   Backup:   {}", Wallet::get_wallet_info(&wallet1_backup));
 
+    // Bonus: Deposit and transaction history
+    println!("\n📜 Bonus: Transaction history...");
+    match Wallet::deposit(&mut wallet1, eth(15)) {
+        Ok(()) => println!("   Deposited 15 ETH into wallet1"),
+        Err(e) => println!("   Error: {}", e),
+    }
+    println!("{}", Wallet::statement(&wallet1));
+
+    // Bonus: Atomic payout to several recipients
+    println!("\n💸 Bonus: Multi-recipient payout...");
+    let mut treasury = Wallet::new_wallet_with_seed(eth(60), "treasury");
+    let mut alice = Wallet::new_wallet_with_seed(0, "alice");
+    let mut bob = Wallet::new_wallet_with_seed(0, "bob");
+    match Wallet::payout(&mut treasury, &mut [(&mut alice, eth(25)), (&mut bob, eth(25))]) {
+        Ok(()) => println!("   Paid 25 ETH each to alice and bob"),
+        Err(e) => println!("   Error: {}", e),
+    }
+    match Wallet::payout(&mut treasury, &mut [(&mut alice, eth(10)), (&mut bob, eth(10))]) {
+        Ok(()) => println!("   Unexpected success"),
+        Err(e) => println!("   Payout rejected: {}", e),
+    }
+    println!("   Treasury: {} ETH, Alice: {} ETH, Bob: {} ETH", Wallet::format_ether(treasury.balance), Wallet::format_ether(alice.balance), Wallet::format_ether(bob.balance));
+
+    // Bonus: Split a wallet into equal parts
+    println!("\n✂️  Bonus: Splitting a wallet...");
+    match Wallet::split(&mut treasury, 3) {
+        Ok(parts) => {
+            for part in &parts {
+                println!("   {}", Wallet::get_wallet_info(part));
+            }
+            println!("   Treasury left with {} ETH", Wallet::format_ether(treasury.balance));
+            match Wallet::merge(parts) {
+                Ok(merged) => println!("   Merged back: {} ({} transactions)", Wallet::get_wallet_info(&merged), merged.history.len()),
+                Err(e) => println!("   Error: {}", e),
+            }
+        }
+        Err(e) => println!("   Error: {}", e),
+    }
+
+    // Bonus: Look up wallets by id through a registry
+    println!("\n🗂️  Bonus: Wallet registry...");
+    let mut registry = WalletRegistry::new();
+    let id1 = wallet1.id.clone();
+    let id2 = wallet2.id.clone();
+    for wallet in [wallet1, wallet2] {
+        if let Err(e) = registry.register(wallet) {
+            println!("   Error: {}", e);
+        }
+    }
+    match registry.transfer(&id1, &id2, eth(5)) {
+        Ok(()) => println!("   Transferred 5 ETH from {} to {}", id1, id2),
+        Err(e) => println!("   Error: {}", e),
+    }
+    if let Some(wallet) = registry.get(&id2) {
+        println!("   {}", Wallet::get_wallet_info(wallet));
+    }
+    match registry.transfer(&id1, "wallet_missing", eth(5)) {
+        Ok(()) => println!("   Unexpected success"),
+        Err(e) => println!("   Error: {}", e),
+    }
+    let registry_path = std::env::temp_dir().join("crypto_wallet_registry.json");
+    let registry_path = registry_path.to_string_lossy();
+    match registry.save(&registry_path).and_then(|_| WalletRegistry::load(&registry_path)) {
+        Ok(loaded) => println!("   Saved and reloaded {} wallets from {}", loaded.wallets.len(), registry_path),
+        Err(e) => println!("   Error persisting registry: {}", e),
+    }
+
+    // Bonus: Owner authorization
+    println!("\n🔐 Bonus: Authorized sends...");
+    let mut owned = Wallet::new_wallet_with_seed(eth(5), "owned");
+    Wallet::assign_owner(&mut owned, "carol");
+    for caller in ["carol", "mallory"] {
+        match Wallet::send_money_authorized(&mut owned, eth(1), caller) {
+            Ok(()) => println!("   {} sent 1 ETH", caller),
+            Err(e) => println!("   {} was rejected: {}", caller, e),
+        }
+    }
+    println!("   {}", Wallet::get_wallet_info(&owned));
+
+    // Bonus: Rate-limited sends
+    println!("\n⏱️  Bonus: Send rate limit...");
+    let mut limited = Wallet::new_wallet_with_seed(eth(5), "limited");
+    Wallet::set_send_limit(&mut limited, 2);
+    let start = now_nanos();
+    for attempt in 1..=3 {
+        match Wallet::send_money_at(&mut limited, eth(1), start) {
+            Ok(()) => println!("   Send {} went through", attempt),
+            Err(e) => println!("   Send {} was rejected: {}", attempt, e),
+        }
+    }
+    if Wallet::send_money_at(&mut limited, eth(1), start + SEND_WINDOW_NANOS).is_ok() {
+        println!("   Window elapsed - sending again");
+    }
+    println!("   {}", Wallet::get_wallet_info(&limited));
+
+    // Bonus: Send fees collected into a treasury
+    println!("\n🏦 Bonus: Fee treasury...");
+    let mut fee_treasury = Treasury::default();
+    let mut payer = Wallet::new_wallet_with_seed(eth(10), "payer");
+    let mut payee = Wallet::new_wallet_with_seed(0, "payee");
+    Wallet::set_send_fee(&mut payer, 50);
+    match Wallet::send_money_with_fee(&mut payer, eth(2), &mut fee_treasury) {
+        Ok(fee) => println!("   Sent 2 ETH, fee {} ETH", Wallet::format_ether(fee)),
+        Err(e) => println!("   Error: {}", e),
+    }
+    match Wallet::transfer_with_fee(&mut payer, &mut payee, eth(4), &mut fee_treasury) {
+        Ok(fee) => println!("   Transferred 4 ETH, fee {} ETH", Wallet::format_ether(fee)),
+        Err(e) => println!("   Error: {}", e),
+    }
+    println!("   Treasury holds {} ETH", Wallet::format_ether(fee_treasury.collected));
+    println!("   {}", Wallet::get_wallet_info(&payer));
+
+    // Bonus: Savings interest
+    println!("\n📈 Bonus: Interest accrual...");
+    let mut savings = Wallet::new_wallet_with_seed(eth(10), "savings");
+    Wallet::snapshot_balance(&mut savings, now_nanos());
+    match Wallet::accrue_interest(&mut savings, 500, 365) {
+        Ok(interest) => println!("   Earned {} ETH at 5% over a year", Wallet::format_ether(interest)),
+        Err(e) => println!("   Error: {}", e),
+    }
+    Wallet::snapshot_balance(&mut savings, now_nanos());
+    println!("   {}", Wallet::get_wallet_info(&savings));
+    let mut compounding = Wallet::new_wallet_with_seed(eth(10), "compounding");
+    match Wallet::accrue_compound(&mut compounding, 500, 5) {
+        Ok(interest) => println!("   Compounded 5% for 5 years: earned {} ETH", Wallet::format_ether(interest)),
+        Err(e) => println!("   Error: {}", e),
+    }
+    println!("   Change since first snapshot: {} wei", Wallet::balance_change(&savings));
+
+    // Bonus: Denominations
+    println!("\n🪙 Bonus: Denominations...");
+    let mut small = Wallet::new_wallet_with_seed(eth(2), "small");
+    match Wallet::send_in(&mut small, 500_000_000, Denomination::Gwei) {
+        Ok(()) => println!("   Sent 500000000 gwei"),
+        Err(e) => println!("   Error: {}", e),
+    }
+    println!("   {}", Wallet::get_wallet_info(&small));
+    println!("   Balance in gwei: {}", Wallet::balance_in(&small, Denomination::Gwei));
+    println!("   Balance in wei:  {}", Wallet::balance_in(&small, Denomination::Wei));
+
     println!("\n✨ Demo completed successfully!");
 }
 
@@ -158,6 +881,33 @@ mod tests {
         assert!(wallet.id.starts_with("wallet_"));
     }
 
+    #[test]
+    fn test_new_wallet_with_seed_is_deterministic() {
+        let wallet = Wallet::new_wallet_with_seed(100, "alice");
+        assert_eq!(wallet.id, "wallet_508b2abb65a03907");
+        assert_eq!(wallet.balance, 100);
+        assert_eq!(Wallet::new_wallet_with_seed(5, "alice").id, wallet.id);
+        assert_ne!(Wallet::new_wallet_with_seed(5, "bob").id, wallet.id);
+    }
+
+    #[test]
+    fn test_denomination_round_trip() {
+        assert_eq!(Denomination::Ether.to_wei(1), Some(1_000_000_000_000_000_000));
+        let wallet = Wallet::new_wallet(Denomination::Ether.to_wei(1).unwrap());
+        assert_eq!(Wallet::balance_in(&wallet, Denomination::Wei), 1_000_000_000_000_000_000);
+        assert_eq!(Wallet::balance_in(&wallet, Denomination::Gwei), 1_000_000_000);
+        assert_eq!(Wallet::balance_in(&wallet, Denomination::Ether), 1);
+    }
+
+    #[test]
+    fn test_send_in_converts_units() {
+        let mut wallet = Wallet::new_wallet(Denomination::Ether.to_wei(2).unwrap());
+        assert!(Wallet::send_in(&mut wallet, 500_000_000, Denomination::Gwei).is_ok());
+        assert_eq!(Wallet::format_ether(wallet.balance), "1.5");
+        assert!(Wallet::get_wallet_info(&wallet).ends_with("Balance: 1.5 ETH"));
+        assert!(Wallet::send_in(&mut wallet, u128::MAX, Denomination::Ether).is_err());
+    }
+
     #[test]
     fn test_check_balance() {
         let wallet = Wallet::new_wallet(50);
@@ -172,6 +922,36 @@ mod tests {
         assert_eq!(wallet.balance, 70);
     }
 
+    #[test]
+    fn test_can_send_matches_send_money() {
+        let mut wallet = Wallet::new_wallet(100);
+        Wallet::set_min_balance(&mut wallet, 20);
+
+        for amount in [10, 80, 81, 200] {
+            let dry_run = Wallet::can_send(&wallet, amount);
+            assert_eq!(wallet.balance, 100);
+            let mut copy = wallet.clone();
+            assert_eq!(dry_run, Wallet::send_money(&mut copy, amount));
+        }
+
+        // Once the rate limit is used up both reject, until the window has passed
+        Wallet::set_send_limit(&mut wallet, 1);
+        let mut limited = wallet.clone();
+        Wallet::send_money_at(&mut limited, 10, 1_000).unwrap();
+        for now in [1_001, 1_000 + SEND_WINDOW_NANOS] {
+            let dry_run = Wallet::can_send_at(&limited, 10, now);
+            assert_eq!(dry_run, Wallet::send_money_at(&mut limited.clone(), 10, now));
+        }
+        assert_eq!(Wallet::can_send_at(&limited, 10, 1_001), Err("rate limit exceeded".to_string()));
+        Wallet::send_money(&mut limited, 10).unwrap();
+        assert_eq!(Wallet::can_send(&limited, 10), Err("rate limit exceeded".to_string()));
+
+        Wallet::freeze(&mut wallet);
+        assert_eq!(Wallet::can_send(&wallet, 10), Err("wallet is frozen".to_string()));
+        assert_eq!(wallet.balance, 100);
+        assert!(wallet.history.is_empty());
+    }
+
     #[test]
     fn test_send_money_insufficient_balance() {
         let mut wallet = Wallet::new_wallet(10);
@@ -180,6 +960,139 @@ mod tests {
         assert_eq!(wallet.balance, 10); // Balance should remain unchanged
     }
 
+    #[test]
+    fn test_send_blocked_by_min_balance() {
+        let mut wallet = Wallet::new_wallet(100);
+        Wallet::set_min_balance(&mut wallet, 40);
+        let result = Wallet::send_money(&mut wallet, 61);
+        assert!(result.unwrap_err().contains("would breach minimum balance"));
+        assert_eq!(wallet.balance, 100);
+
+        let mut other = Wallet::new_wallet(0);
+        assert!(Wallet::transfer_between(&mut wallet, &mut other, 61).is_err());
+        assert_eq!(other.balance, 0);
+    }
+
+    #[test]
+    fn test_send_allowed_up_to_min_balance() {
+        let mut wallet = Wallet::new_wallet(100);
+        Wallet::set_min_balance(&mut wallet, 40);
+        assert!(Wallet::send_money(&mut wallet, 60).is_ok());
+        assert_eq!(wallet.balance, 40);
+        assert!(Wallet::send_money(&mut wallet, 1).is_err());
+    }
+
+    #[test]
+    fn test_frozen_wallet_blocks_send_until_unfrozen() {
+        let mut wallet = Wallet::new_wallet(100);
+        Wallet::freeze(&mut wallet);
+        assert_eq!(Wallet::send_money(&mut wallet, 10), Err("wallet is frozen".to_string()));
+        assert_eq!(Wallet::check_balance(&wallet), 100);
+        assert!(Wallet::deposit(&mut wallet, 10).is_err());
+
+        Wallet::unfreeze(&mut wallet);
+        assert!(Wallet::send_money(&mut wallet, 10).is_ok());
+        assert_eq!(Wallet::check_balance(&wallet), 90);
+    }
+
+    #[test]
+    fn test_transfer_to_frozen_wallet_fails() {
+        let mut from = Wallet::new_wallet(100);
+        let mut to = Wallet::new_wallet(0);
+        Wallet::freeze(&mut to);
+        assert!(Wallet::transfer_between(&mut from, &mut to, 10).is_err());
+        assert_eq!((from.balance, to.balance), (100, 0));
+    }
+
+    #[test]
+    fn test_send_money_authorized() {
+        let mut wallet = Wallet::new_wallet(100);
+        Wallet::assign_owner(&mut wallet, "alice");
+        assert!(Wallet::send_money_authorized(&mut wallet, 30, "alice").is_ok());
+        assert_eq!(wallet.balance, 70);
+    }
+
+    #[test]
+    fn test_send_money_unauthorized() {
+        let mut wallet = Wallet::new_wallet(100);
+        Wallet::assign_owner(&mut wallet, "alice");
+        let result = Wallet::send_money_authorized(&mut wallet, 30, "mallory");
+        assert_eq!(result, Err("unauthorized".to_string()));
+        assert_eq!(wallet.balance, 100);
+
+        let mut unowned = Wallet::new_wallet(100);
+        assert!(Wallet::send_money_authorized(&mut unowned, 30, "").is_err());
+    }
+
+    #[test]
+    fn test_send_rate_limit_rejects_extra_send_in_window() {
+        let mut wallet = Wallet::new_wallet(100);
+        Wallet::set_send_limit(&mut wallet, 3);
+        let start = 1_000;
+        for i in 0..3 {
+            assert!(Wallet::send_money_at(&mut wallet, 10, start + i).is_ok());
+        }
+        assert_eq!(Wallet::send_money_at(&mut wallet, 10, start + 3), Err("rate limit exceeded".to_string()));
+        assert_eq!(wallet.balance, 70);
+        assert_eq!(wallet.history.len(), 3);
+
+        assert!(Wallet::send_money_at(&mut wallet, 10, start + SEND_WINDOW_NANOS).is_ok());
+        assert_eq!(wallet.sends_this_window, 1);
+    }
+
+    #[test]
+    fn test_failed_send_does_not_use_rate_limit() {
+        let mut wallet = Wallet::new_wallet(10);
+        Wallet::set_send_limit(&mut wallet, 1);
+        assert!(Wallet::send_money_at(&mut wallet, 20, 0).is_err());
+        assert!(Wallet::send_money_at(&mut wallet, 5, 1).is_ok());
+    }
+
+    #[test]
+    fn test_treasury_collects_fees_from_three_sends() {
+        let mut treasury = Treasury::default();
+        let mut wallet = Wallet::new_wallet(10_000_000);
+        let mut other = Wallet::new_wallet(0);
+        Wallet::set_send_fee(&mut wallet, 100);
+
+        assert_eq!(Wallet::send_money_with_fee(&mut wallet, 1_000_000, &mut treasury), Ok(10_000));
+        assert_eq!(Wallet::send_money_with_fee(&mut wallet, 2_000_000, &mut treasury), Ok(20_000));
+        assert_eq!(Wallet::transfer_with_fee(&mut wallet, &mut other, 3_000_000, &mut treasury), Ok(30_000));
+
+        assert_eq!(treasury.collected, 60_000);
+        assert_eq!(wallet.balance, 10_000_000 - 6_000_000 - 60_000);
+        assert_eq!(other.balance, 3_000_000);
+        assert_eq!(wallet.history.iter().filter(|tx| tx.kind == TxKind::Fee).count(), 3);
+    }
+
+    #[test]
+    fn test_fee_transfer_is_not_rate_limited() {
+        let mut treasury = Treasury::default();
+        let mut wallet = Wallet::new_wallet(10_000);
+        let mut other = Wallet::new_wallet(0);
+        Wallet::set_send_fee(&mut wallet, 100);
+        Wallet::set_send_limit(&mut wallet, 1);
+
+        assert_eq!(Wallet::send_money_with_fee(&mut wallet, 1_000, &mut treasury), Ok(10));
+        assert_eq!(Wallet::send_money_with_fee(&mut wallet, 1_000, &mut treasury), Err("rate limit exceeded".to_string()));
+        // Transfers neither hit the limit nor use it up
+        assert_eq!(Wallet::transfer_with_fee(&mut wallet, &mut other, 1_000, &mut treasury), Ok(10));
+        assert!(Wallet::transfer_between(&mut wallet, &mut other, 1_000).is_ok());
+        assert_eq!(wallet.sends_this_window, 1);
+        assert_eq!(other.balance, 2_000);
+    }
+
+    #[test]
+    fn test_send_with_fee_needs_balance_for_fee() {
+        let mut treasury = Treasury::default();
+        let mut wallet = Wallet::new_wallet(1_000_000);
+        Wallet::set_send_fee(&mut wallet, 100);
+
+        assert!(Wallet::send_money_with_fee(&mut wallet, 1_000_000, &mut treasury).is_err());
+        assert_eq!(wallet.balance, 1_000_000);
+        assert_eq!(treasury, Treasury::default());
+    }
+
     #[test]
     fn test_transfer_between() {
         let mut wallet1 = Wallet::new_wallet(100);
@@ -191,6 +1104,53 @@ mod tests {
         assert_eq!(wallet2.balance, 80);
     }
 
+    #[test]
+    fn test_transfer_overflow_leaves_both_wallets_untouched() {
+        let mut from = Wallet::new_wallet(100);
+        let mut to = Wallet::new_wallet(u128::MAX - 10);
+
+        assert!(Wallet::transfer_between(&mut from, &mut to, 30).is_err());
+        assert_eq!((from.balance, to.balance), (100, u128::MAX - 10));
+        assert!(from.history.is_empty() && to.history.is_empty());
+    }
+
+    #[test]
+    fn test_payout_three_way() {
+        let mut source = Wallet::new_wallet_with_seed(100, "source");
+        let mut a = Wallet::new_wallet_with_seed(0, "a");
+        let mut b = Wallet::new_wallet_with_seed(10, "b");
+        let mut c = Wallet::new_wallet_with_seed(20, "c");
+
+        let result = Wallet::payout(&mut source, &mut [(&mut a, 10), (&mut b, 20), (&mut c, 30)]);
+        assert!(result.is_ok());
+        assert_eq!(source.balance, 40);
+        assert_eq!((a.balance, b.balance, c.balance), (10, 30, 50));
+        assert_eq!(source.history.len(), 3);
+    }
+
+    #[test]
+    fn test_payout_exceeding_balance_leaves_wallets_untouched() {
+        let mut source = Wallet::new_wallet_with_seed(50, "source");
+        let mut a = Wallet::new_wallet_with_seed(0, "a");
+        let mut b = Wallet::new_wallet_with_seed(10, "b");
+
+        let result = Wallet::payout(&mut source, &mut [(&mut a, 30), (&mut b, 30)]);
+        assert!(result.is_err());
+        assert_eq!((source.balance, a.balance, b.balance), (50, 0, 10));
+        assert!(source.history.is_empty() && a.history.is_empty() && b.history.is_empty());
+    }
+
+    #[test]
+    fn test_payout_overflow_rolls_back() {
+        let mut source = Wallet::new_wallet_with_seed(100, "source");
+        let mut a = Wallet::new_wallet_with_seed(0, "a");
+        let mut b = Wallet::new_wallet_with_seed(u128::MAX, "b");
+
+        let result = Wallet::payout(&mut source, &mut [(&mut a, 10), (&mut b, 10)]);
+        assert!(result.is_err());
+        assert_eq!((source.balance, a.balance, b.balance), (100, 0, u128::MAX));
+    }
+
     #[test]
     fn test_batch_check() {
         let wallets = vec![
@@ -201,6 +1161,43 @@ mod tests {
         assert_eq!(Wallet::batch_check(&wallets), 175);
     }
 
+    #[test]
+    fn test_balance_summary_in_ether() {
+        let wallets = vec![
+            Wallet::new_wallet(WEI_PER_ETHER),
+            Wallet::new_wallet(3 * WEI_PER_ETHER),
+        ];
+        let summary = Wallet::balance_summary(&wallets);
+        assert_eq!(summary, Summary { count: 2, sum: 4.0, min: 1.0, max: 3.0, mean: 2.0 });
+    }
+
+    #[test]
+    fn test_batch_stats_empty() {
+        let stats = Wallet::batch_stats(&[]).unwrap();
+        assert_eq!(stats, WalletStats { total: 0, count: 0, min: 0, max: 0, average: 0.0 });
+    }
+
+    #[test]
+    fn test_batch_stats_three_wallets() {
+        let wallets = vec![
+            Wallet::new_wallet(100),
+            Wallet::new_wallet(50),
+            Wallet::new_wallet(30),
+        ];
+        let stats = Wallet::batch_stats(&wallets).unwrap();
+        assert_eq!(stats.total, 180);
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.min, 30);
+        assert_eq!(stats.max, 100);
+        assert_eq!(stats.average, 60.0);
+    }
+
+    #[test]
+    fn test_batch_stats_total_overflow() {
+        let wallets = vec![Wallet::new_wallet(u128::MAX / 2 + 1), Wallet::new_wallet(u128::MAX / 2 + 1)];
+        assert_eq!(Wallet::batch_stats(&wallets), Err("Total balance overflows u128".to_string()));
+    }
+
     #[test]
     fn test_clone_wallet() {
         let original = Wallet::new_wallet(100);
@@ -208,4 +1205,222 @@ mod tests {
         assert_eq!(original.balance, cloned.balance);
         assert_eq!(original.id, cloned.id);
     }
+
+    #[test]
+    fn test_history_records_send_then_receive() {
+        let mut wallet1 = Wallet::new_wallet(100);
+        let mut wallet2 = Wallet::new_wallet(50);
+
+        Wallet::send_money(&mut wallet1, 10).unwrap();
+        Wallet::transfer_between(&mut wallet2, &mut wallet1, 25).unwrap();
+
+        assert_eq!(wallet1.history.len(), 2);
+        assert_eq!(wallet1.history[0].kind, TxKind::Sent);
+        assert_eq!(wallet1.history[0].amount, 10);
+        assert_eq!(wallet1.history[1].kind, TxKind::Received);
+        assert_eq!(wallet1.history[1].amount, 25);
+        assert!(wallet1.history[0].timestamp <= wallet1.history[1].timestamp);
+        assert_eq!(wallet2.history[0].kind, TxKind::Sent);
+    }
+
+    #[test]
+    fn test_split_assigns_remainder_to_first() {
+        let mut wallet = Wallet::new_wallet(100);
+        let parts = Wallet::split(&mut wallet, 3).unwrap();
+
+        let balances: Vec<u128> = parts.iter().map(|w| w.balance).collect();
+        assert_eq!(balances, vec![34, 33, 33]);
+        assert_eq!(balances.iter().sum::<u128>(), 100);
+        assert_eq!(wallet.balance, 0);
+        assert_ne!(parts[0].id, parts[1].id);
+    }
+
+    #[test]
+    fn test_split_keeps_reserve() {
+        let mut wallet = Wallet::new_wallet(100);
+        Wallet::set_min_balance(&mut wallet, 40);
+        let parts = Wallet::split(&mut wallet, 2).unwrap();
+        assert_eq!(parts.iter().map(|w| w.balance).collect::<Vec<_>>(), vec![30, 30]);
+        assert_eq!(wallet.balance, 40);
+    }
+
+    #[test]
+    fn test_split_same_wallet_twice_registers_all_parts() {
+        let mut registry = WalletRegistry::new();
+        let mut wallet = Wallet::new_wallet_with_seed(100, "splitter");
+        let first = Wallet::split(&mut wallet, 2).unwrap();
+        Wallet::deposit(&mut wallet, 100).unwrap();
+        let second = Wallet::split(&mut wallet, 2).unwrap();
+
+        for part in first.into_iter().chain(second) {
+            registry.register(part).unwrap();
+        }
+        assert_eq!(registry.wallets.len(), 4);
+        assert_eq!(registry.wallets.values().map(|w| w.balance).sum::<u128>(), 200);
+    }
+
+    #[test]
+    fn test_split_into_zero_parts() {
+        let mut wallet = Wallet::new_wallet(100);
+        assert!(Wallet::split(&mut wallet, 0).is_err());
+        assert_eq!(wallet.balance, 100);
+    }
+
+    #[test]
+    fn test_merge_three_wallets() {
+        let mut first = Wallet::new_wallet_with_seed(10, "first");
+        let second = Wallet::new_wallet_with_seed(20, "second");
+        let mut third = Wallet::new_wallet_with_seed(30, "third");
+        Wallet::deposit(&mut third, 5).unwrap();
+        Wallet::deposit(&mut first, 1).unwrap();
+
+        let merged = Wallet::merge(vec![first, second, third]).unwrap();
+        assert_eq!(merged.balance, 66);
+        assert_eq!(merged.history.len(), 2);
+        assert!(merged.history[0].timestamp <= merged.history[1].timestamp);
+        assert_eq!(merged.history[0].amount, 5);
+    }
+
+    #[test]
+    fn test_merge_overflow_and_empty() {
+        let wallets = vec![Wallet::new_wallet(u128::MAX), Wallet::new_wallet(1)];
+        assert_eq!(Wallet::merge(wallets).unwrap_err(), "Merged balance overflows u128");
+        assert!(Wallet::merge(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_balance_change_positive() {
+        let mut wallet = Wallet::new_wallet(100);
+        Wallet::snapshot_balance(&mut wallet, 1_000);
+        Wallet::deposit(&mut wallet, 50).unwrap();
+        Wallet::snapshot_balance(&mut wallet, 2_000);
+
+        assert_eq!(wallet.balance_history, vec![(1_000, 100), (2_000, 150)]);
+        assert_eq!(Wallet::balance_change(&wallet), 50);
+    }
+
+    #[test]
+    fn test_balance_change_negative() {
+        let mut wallet = Wallet::new_wallet(100);
+        assert_eq!(Wallet::balance_change(&wallet), 0);
+        Wallet::snapshot_balance(&mut wallet, 1_000);
+        Wallet::send_money(&mut wallet, 70).unwrap();
+        Wallet::snapshot_balance(&mut wallet, 2_000);
+
+        assert_eq!(Wallet::balance_change(&wallet), -70);
+    }
+
+    #[test]
+    fn test_compound_beats_simple_interest() {
+        let mut simple = Wallet::new_wallet(1_000_000);
+        let mut compound = Wallet::new_wallet(1_000_000);
+
+        let simple_interest = Wallet::accrue_interest(&mut simple, 1_000, 365 * 3).unwrap();
+        let compound_interest = Wallet::accrue_compound(&mut compound, 1_000, 3).unwrap();
+
+        assert_eq!(simple_interest, 300_000);
+        // 1_000_000 -> 1_100_000 -> 1_210_000 -> 1_331_000
+        assert_eq!(compound_interest, 331_000);
+        assert!(compound.balance > simple.balance);
+    }
+
+    #[test]
+    fn test_compound_single_period_matches_simple() {
+        let mut wallet = Wallet::new_wallet(999);
+        assert_eq!(Wallet::accrue_compound(&mut wallet, 500, 1), Ok(49));
+        assert_eq!(wallet.balance, 1_048);
+        assert_eq!(Wallet::accrue_compound(&mut wallet, 500, 0), Ok(0));
+    }
+
+    #[test]
+    fn test_accrue_simple_interest() {
+        let mut wallet = Wallet::new_wallet(100_000);
+        assert_eq!(Wallet::accrue_interest(&mut wallet, 500, 365), Ok(5000));
+        assert_eq!(wallet.balance, 105_000);
+        assert_eq!(wallet.history[0].kind, TxKind::Interest);
+        assert_eq!(wallet.history[0].amount, 5000);
+    }
+
+    #[test]
+    fn test_accrue_interest_rounds_down() {
+        let mut wallet = Wallet::new_wallet(1000);
+        // 1000 * 500 / 10000 * 30 / 365 = 4.1 -> 4
+        assert_eq!(Wallet::accrue_interest(&mut wallet, 500, 30), Ok(4));
+        assert_eq!(wallet.balance, 1004);
+    }
+
+    #[test]
+    fn test_frozen_wallet_earns_no_interest() {
+        let mut wallet = Wallet::new_wallet(1000);
+        Wallet::freeze(&mut wallet);
+        assert_eq!(Wallet::accrue_interest(&mut wallet, 500, 365), Err("wallet is frozen".to_string()));
+        assert_eq!(Wallet::accrue_compound(&mut wallet, 500, 1), Err("wallet is frozen".to_string()));
+        assert_eq!(wallet.balance, 1000);
+        assert!(wallet.history.is_empty());
+    }
+
+    #[test]
+    fn test_deposit_and_statement() {
+        let mut wallet = Wallet::new_wallet(0);
+        Wallet::deposit(&mut wallet, 40).unwrap();
+        assert_eq!(wallet.balance, 40);
+        assert_eq!(wallet.history[0].kind, TxKind::Deposit);
+        assert!(Wallet::statement(&wallet).contains(&format!("Closing balance: {} ETH", Wallet::format_ether(40))));
+    }
+
+    #[test]
+    fn test_registry_transfer() {
+        let mut registry = WalletRegistry::new();
+        let wallet1 = Wallet::new_wallet_with_seed(100, "alice");
+        let wallet2 = Wallet::new_wallet_with_seed(50, "bob");
+        let (id1, id2) = (wallet1.id.clone(), wallet2.id.clone());
+        registry.register(wallet1).unwrap();
+        registry.register(wallet2).unwrap();
+
+        assert!(registry.transfer(&id1, &id2, 30).is_ok());
+        assert_eq!(registry.get(&id1).unwrap().balance, 70);
+        assert_eq!(registry.get(&id2).unwrap().balance, 80);
+        assert!(registry.transfer(&id1, &id1, 10).is_err());
+    }
+
+    #[test]
+    fn test_registry_missing_source_id() {
+        let mut registry = WalletRegistry::new();
+        let wallet = Wallet::new_wallet(100);
+        let id = wallet.id.clone();
+        registry.register(wallet).unwrap();
+
+        let result = registry.transfer("wallet_missing", &id, 10);
+        assert_eq!(result, Err("Source wallet wallet_missing not found".to_string()));
+        assert_eq!(registry.get(&id).unwrap().balance, 100);
+    }
+
+    #[test]
+    fn test_registry_save_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("wallet_registry_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let mut registry = WalletRegistry::new();
+        let mut alice = Wallet::new_wallet_with_seed(100, "alice");
+        Wallet::deposit(&mut alice, 5).unwrap();
+        registry.register(alice).unwrap();
+        registry.register(Wallet::new_wallet_with_seed(50, "bob")).unwrap();
+        registry.save(path).unwrap();
+
+        let loaded = WalletRegistry::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.wallets.len(), 2);
+        for (id, wallet) in &registry.wallets {
+            let restored = loaded.get(id).unwrap();
+            assert_eq!(restored.id, wallet.id);
+            assert_eq!(restored.balance, wallet.balance);
+            assert_eq!(restored.history.len(), wallet.history.len());
+        }
+    }
+
+    #[test]
+    fn test_registry_load_missing_file_is_empty() {
+        let loaded = WalletRegistry::load("/nonexistent/dir/wallets.json").unwrap();
+        assert!(loaded.wallets.is_empty());
+    }
 }
\ No newline at end of file
@@ -1,3 +1,9 @@
+use std::io::{IsTerminal, Write};
+
+#[path = "../../shared/parse.rs"]
+#[allow(dead_code)]
+mod parse;
+
 fn main() {
     println!("Enter how many Fibonacci terms to generate (max 45):");
 
@@ -7,31 +13,474 @@ fn main() {
         return;
     }
 
-    let num: u32 = match input.trim().parse() {
-        Ok(n) if n <= 45 => n,
+    let num = match parse::parse_positive_u64(&input) {
+        Ok(n) if n <= 45 => n as u32,
         Ok(_) => {
             println!("Please enter a number up to 45.");
             return;
         }
-        Err(_) => {
-            println!("Invalid input. Please enter a non-negative number.");
+        Err(e) => {
+            println!("Invalid input: {}", e);
             return;
         }
     };
 
-    generate_fibonacci(num);
+    println!("Output base - (D)ecimal, (H)ex or (B)inary? [D]:");
+    let mut base_input = String::new();
+    let base = match std::io::stdin().read_line(&mut base_input) {
+        Ok(_) => parse_base(&base_input).unwrap_or_else(|e| {
+            println!("{} - using decimal.", e);
+            Base::Dec
+        }),
+        Err(_) => Base::Dec,
+    };
+
+    generate_fibonacci(num, base);
+
+    if let (Some(total), Some(even)) = (sum_first_n(num), sum_even_indexed(num)) {
+        println!("\n Sum of these terms: {} (even-indexed terms: {})", total, even);
+    }
+
+    if let Some(&last) = fibonacci_sequence(num).last()
+        && let Some(index) = fibonacci_index_fast(last as u128)
+    {
+        println!("\n Last term {} is F({}), found by closed-form lookup", last, index);
+    }
+
+    let indices: Vec<u64> = (1..=4).map(|m| m * num as u64).collect();
+    println!("\n F{:?} computed in parallel: {:?}", indices, fib_batch_parallel(&indices));
+
+    println!("\n Tribonacci ({} terms): {:?}", num.min(10), k_bonacci(3, num.min(10)));
+
+    let path = std::env::temp_dir().join("fibonacci.txt");
+    let written = std::fs::File::create(&path).and_then(|file| stream_fibonacci_to(&mut std::io::BufWriter::new(file), num));
+    match written {
+        Ok(()) => println!("\n Streamed {} terms to {}", num, path.display()),
+        Err(e) => println!("\n Could not write {}: {}", path.display(), e),
+    }
+
+    // F(0)..F(186) - every term that fits in a u128
+    print_benford(&k_bonacci(2, 187));
+
+    println!("\n Retry backoff for {} attempts at 100 ms: {:?}", num.min(10), fibonacci_backoff(num.min(10), 100));
 }
 
-fn generate_fibonacci(num: u32) {
-    let (mut a, mut b) = (0, 1);
-    println!("\n Fibonacci sequence ({} terms):\n", num);
-    println!("{:<6} | {:>20}", "Index", "Value");
-    println!("-------------------------------");
+// The first `count` terms, starting F(0) = 0. Terms past u64::MAX saturate.
+fn fibonacci_sequence(count: u32) -> Vec<u64> {
+    let mut sequence = Vec::with_capacity(count as usize);
+    let (mut a, mut b): (u64, u64) = (0, 1);
+    for _ in 0..count {
+        sequence.push(a);
+        let temp = a.saturating_add(b);
+        a = b;
+        b = temp;
+    }
+    sequence
+}
+
+// Terms written between flushes in stream_fibonacci_to
+const STREAM_FLUSH_EVERY: u32 = 1000;
 
+// Write the first `num` terms one per line as they are generated, keeping only the last two
+// terms in memory. Fails with InvalidInput at the first term past u128::MAX (F(187)).
+fn stream_fibonacci_to<W: Write>(writer: &mut W, num: u32) -> std::io::Result<()> {
+    let (mut a, mut b) = (Some(0u128), Some(1u128));
     for i in 0..num {
-        println!("{:<6} | {:>20}", i, a);
-        let temp = a + b;
+        let term = a.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("F({}) does not fit in a u128", i)))?;
+        writeln!(writer, "{}", term)?;
+        if (i + 1) % STREAM_FLUSH_EVERY == 0 {
+            writer.flush()?;
+        }
+        (a, b) = (b, b.and_then(|next| term.checked_add(next)));
+    }
+    writer.flush()
+}
+
+// One line of the printed table: the term, how many digits it has and its ratio to the
+// previous term (None while the previous term is zero)
+#[derive(Debug, Clone, PartialEq)]
+struct FibRow {
+    index: u32,
+    value: u128,
+    digits: usize,
+    ratio: Option<f64>,
+}
+
+fn fib_rows(count: u32) -> Vec<FibRow> {
+    let terms = k_bonacci(2, count);
+    terms
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| FibRow {
+            index: i as u32,
+            value,
+            digits: digit_count(value),
+            ratio: match i.checked_sub(1).map(|prev| terms[prev]) {
+                Some(prev) if prev > 0 => Some(value as f64 / prev as f64),
+                _ => None,
+            },
+        })
+        .collect()
+}
+
+// Number of decimal digits, counting 0 as one digit
+fn digit_count(n: u128) -> usize {
+    n.checked_ilog10().map_or(1, |log| log as usize + 1)
+}
+
+// Radix the table's Value column is printed in
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Base {
+    #[default]
+    Dec,
+    Hex,
+    Bin,
+}
+
+// Blank input keeps the decimal default
+fn parse_base(input: &str) -> Result<Base, String> {
+    match input.trim().to_lowercase().as_str() {
+        "" | "d" | "dec" => Ok(Base::Dec),
+        "h" | "hex" => Ok(Base::Hex),
+        "b" | "bin" => Ok(Base::Bin),
+        other => Err(format!("'{}' is not a base", other)),
+    }
+}
+
+// Hex and binary get the usual 0x / 0b prefix
+fn format_in_base(n: u128, base: Base) -> String {
+    match base {
+        Base::Dec => n.to_string(),
+        Base::Hex => format!("{:#x}", n),
+        Base::Bin => format!("{:#b}", n),
+    }
+}
+
+fn generate_fibonacci(num: u32, base: Base) {
+    // Binary values outgrow the decimal column, so size it to the widest value
+    let rows = fib_rows(num);
+    let values: Vec<String> = rows.iter().map(|fib_row| format_in_base(fib_row.value, base)).collect();
+    let width = values.iter().map(String::len).max().unwrap_or(0).max(20);
+
+    println!("\n Fibonacci sequence ({} terms):\n", num);
+    println!("{:<6} | {:>width$} | {:>6} | {:>10}", "Index", "Value", "Digits", "Ratio");
+    println!("{}", "-".repeat(width + 30));
+
+    // Every other row is tinted and the final term highlighted
+    for (i, (fib_row, value)) in rows.iter().zip(&values).enumerate() {
+        let ratio = fib_row.ratio.map_or("-".to_string(), |r| format!("{:.6}", r));
+        let row = format!("{:<6} | {:>width$} | {:>6} | {:>10}", fib_row.index, value, fib_row.digits, ratio);
+        if i + 1 == rows.len() {
+            println!("{}", colorize(&row, 33));
+        } else if i % 2 == 1 {
+            println!("{}", colorize(&row, 36));
+        } else {
+            println!("{}", row);
+        }
+    }
+}
+
+// F(n) computed iteratively, or None once it no longer fits in a u128 (n > 186)
+fn fib_nth(n: u64) -> Option<u128> {
+    if n == 0 {
+        return Some(0);
+    }
+    let (mut a, mut b): (u128, u128) = (0, 1);
+    for _ in 1..n {
+        let next = a.checked_add(b)?;
         a = b;
-        b = temp;
+        b = next;
+    }
+    Some(b)
+}
+
+// Index of a Fibonacci number without walking the sequence: F(i) is the nearest
+// integer to φ^i / √5, so i = round(log_φ(n * √5)). The estimate is confirmed
+// with fib_nth (checking its neighbours too) since f64 loses precision for large n.
+// 1 is both F(1) and F(2); the lower index is returned.
+fn fibonacci_index_fast(n: u128) -> Option<u64> {
+    match n {
+        0 => return Some(0),
+        1 => return Some(1),
+        _ => {}
+    }
+    let phi = (1.0 + 5f64.sqrt()) / 2.0;
+    let estimate = ((n as f64 * 5f64.sqrt()).ln() / phi.ln()).round() as u64;
+    (estimate.saturating_sub(1)..=estimate + 1).find(|&i| fib_nth(i) == Some(n))
+}
+
+// fib_nth for each index, split across worker threads and returned in input order.
+// Indices past F(186) saturate to u128::MAX.
+fn fib_batch_parallel(indices: &[u64]) -> Vec<u128> {
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = indices.len().div_ceil(workers).max(1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = indices
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || chunk.iter().map(|&i| fib_nth(i).unwrap_or(u128::MAX)).collect::<Vec<_>>())
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Fibonacci worker thread panicked"))
+            .collect()
+    })
+}
+
+// How many terms start with each digit 1-9 (index 0 is digit 1). Zeros have no leading digit
+// and are skipped.
+fn leading_digit_distribution(seq: &[u128]) -> [u32; 9] {
+    let mut counts = [0u32; 9];
+    for &n in seq {
+        if let Some(log) = n.checked_ilog10() {
+            let leading = n / 10u128.pow(log);
+            counts[leading as usize - 1] += 1;
+        }
+    }
+    counts
+}
+
+// Observed leading-digit shares next to Benford's law, P(d) = log10(1 + 1/d)
+fn print_benford(seq: &[u128]) {
+    let counts = leading_digit_distribution(seq);
+    let total: u32 = counts.iter().sum();
+    println!("\n Leading digits of {} terms vs Benford's law:", total);
+    println!("{:<6} | {:>6} | {:>8} | {:>8}", "Digit", "Count", "Observed", "Benford");
+    for (i, &count) in counts.iter().enumerate() {
+        let digit = i + 1;
+        let observed = if total == 0 { 0.0 } else { count as f64 / total as f64 * 100.0 };
+        let expected = (1.0 + 1.0 / digit as f64).log10() * 100.0;
+        println!("{:<6} | {:>6} | {:>7.1}% | {:>7.1}%", digit, count, observed, expected);
+    }
+}
+
+// Sum of the first n terms F(0)..F(n-1), via the identity F(0) + ... + F(n-1) = F(n+1) - 1.
+// None if F(n+1) doesn't fit in a u128.
+fn sum_first_n(n: u32) -> Option<u128> {
+    fib_nth(n as u64 + 1)?.checked_sub(1)
+}
+
+// Sum of the even-indexed terms among the first n, F(0) + F(2) + ... + F(2k) with 2k < n,
+// via the identity F(0) + F(2) + ... + F(2k) = F(2k+1) - 1.
+fn sum_even_indexed(n: u32) -> Option<u128> {
+    if n == 0 {
+        return Some(0);
+    }
+    let k = (n as u64 - 1) / 2;
+    fib_nth(2 * k + 1)?.checked_sub(1)
+}
+
+// The first `num` terms where each is the sum of the previous k, seeded with k-1 zeros then a 1.
+// k = 2 is Fibonacci, k = 3 Tribonacci. Terms past u128::MAX saturate. Panics if k is 0.
+fn k_bonacci(k: usize, num: u32) -> Vec<u128> {
+    assert!(k >= 1, "k_bonacci needs k >= 1, got {}", k);
+    let mut terms: Vec<u128> = Vec::with_capacity(num as usize);
+    for i in 0..num as usize {
+        let next = if i + 1 < k {
+            0
+        } else if i + 1 == k {
+            1
+        } else {
+            terms[i - k..].iter().fold(0, |sum: u128, &term| sum.saturating_add(term))
+        };
+        terms.push(next);
+    }
+    terms
+}
+
+// Wrap text in an ANSI colour code, or leave it plain when colours are disabled
+fn colorize(s: &str, code: u8) -> String {
+    paint(s, code, colors_enabled())
+}
+
+fn paint(s: &str, code: u8, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", code, s)
+    } else {
+        s.to_string()
+    }
+}
+
+// Colours only go to a terminal, and never when NO_COLOR is set (https://no-color.org)
+fn colors_enabled() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    color_allowed(no_color, std::io::stdout().is_terminal())
+}
+
+fn color_allowed(no_color: bool, is_terminal: bool) -> bool {
+    !no_color && is_terminal
+}
+
+// Retry delays of base_ms * F(i) for i in 1..=max_retries, saturating instead of overflowing
+fn fibonacci_backoff(max_retries: u32, base_ms: u64) -> Vec<u64> {
+    fibonacci_sequence(max_retries + 1)
+        .into_iter()
+        .skip(1)
+        .map(|term| term.saturating_mul(base_ms))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fibonacci_sequence_first_terms() {
+        assert_eq!(fibonacci_sequence(8), vec![0, 1, 1, 2, 3, 5, 8, 13]);
+        assert!(fibonacci_sequence(0).is_empty());
+    }
+
+    #[test]
+    fn test_fibonacci_index_fast() {
+        assert_eq!(fibonacci_index_fast(832040), Some(30));
+        assert_eq!(fibonacci_index_fast(832041), None);
+        assert_eq!(fibonacci_index_fast(0), Some(0));
+        assert_eq!(fibonacci_index_fast(1), Some(1));
+        assert_eq!(fibonacci_index_fast(2), Some(3));
+        assert_eq!(fibonacci_index_fast(4), None);
+    }
+
+    #[test]
+    fn test_fibonacci_index_fast_matches_every_term() {
+        for i in 3..=186 {
+            let value = fib_nth(i).unwrap();
+            assert_eq!(fibonacci_index_fast(value), Some(i));
+            if i > 4 {
+                assert_eq!(fibonacci_index_fast(value + 1), None, "F({}) + 1", i);
+            }
+        }
+        assert_eq!(fib_nth(187), None);
+    }
+
+    #[test]
+    fn test_fib_batch_parallel_matches_sequential() {
+        let indices = [10, 20, 50, 90];
+        let sequential: Vec<u128> = indices.iter().map(|&i| fib_nth(i).unwrap()).collect();
+        assert_eq!(fib_batch_parallel(&indices), sequential);
+        assert_eq!(fib_batch_parallel(&[200, 1]), vec![u128::MAX, 1]);
+        assert!(fib_batch_parallel(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_sum_first_n() {
+        assert_eq!(sum_first_n(10), Some(88));
+        assert_eq!(sum_first_n(0), Some(0));
+        assert_eq!(sum_first_n(185), fib_nth(186).map(|f| f - 1));
+        assert_eq!(sum_first_n(186), None);
+    }
+
+    #[test]
+    fn test_sum_even_indexed() {
+        // 0 + 1 + 3 + 8 + 21
+        assert_eq!(sum_even_indexed(10), Some(33));
+        assert_eq!(sum_even_indexed(0), Some(0));
+        for n in 1..40 {
+            let brute: u128 = (0..n as u64).step_by(2).map(|i| fib_nth(i).unwrap()).sum();
+            assert_eq!(sum_even_indexed(n), Some(brute));
+        }
+    }
+
+    #[test]
+    fn test_k_bonacci_tribonacci() {
+        assert_eq!(k_bonacci(3, 8), vec![0, 0, 1, 1, 2, 4, 7, 13]);
+    }
+
+    #[test]
+    fn test_k_bonacci_two_is_fibonacci() {
+        let fibonacci: Vec<u128> = fibonacci_sequence(20).into_iter().map(u128::from).collect();
+        assert_eq!(k_bonacci(2, 20), fibonacci);
+        assert_eq!(k_bonacci(1, 4), vec![1, 1, 1, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "k >= 1")]
+    fn test_k_bonacci_rejects_zero() {
+        k_bonacci(0, 5);
+    }
+
+    #[test]
+    fn test_digit_count() {
+        assert_eq!(digit_count(0), 1);
+        assert_eq!(digit_count(9), 1);
+        assert_eq!(digit_count(10), 2);
+        assert_eq!(digit_count(fib_nth(100).unwrap()), 21);
+    }
+
+    #[test]
+    fn test_fib_rows() {
+        let rows = fib_rows(4);
+        assert_eq!(rows[0], FibRow { index: 0, value: 0, digits: 1, ratio: None });
+        assert_eq!(rows[1].ratio, None);
+        assert_eq!(rows[3], FibRow { index: 3, value: 2, digits: 1, ratio: Some(2.0) });
+    }
+
+    #[test]
+    fn test_format_in_base() {
+        let f10 = fib_nth(10).unwrap();
+        assert_eq!(f10, 55);
+        assert_eq!(format_in_base(f10, Base::Dec), "55");
+        assert_eq!(format_in_base(f10, Base::Hex), "0x37");
+        assert_eq!(format_in_base(f10, Base::Bin), "0b110111");
+    }
+
+    #[test]
+    fn test_parse_base() {
+        assert_eq!(parse_base("\n"), Ok(Base::Dec));
+        assert_eq!(parse_base(" HEX\n"), Ok(Base::Hex));
+        assert_eq!(parse_base("b"), Ok(Base::Bin));
+        assert!(parse_base("octal").is_err());
+    }
+
+    #[test]
+    fn test_leading_digit_distribution() {
+        // 0, 1, 1, 2, 3, 5, 8, 13, 21, 34
+        let counts = leading_digit_distribution(&fibonacci_sequence(10).iter().map(|&n| n as u128).collect::<Vec<_>>());
+        assert_eq!(counts, [3, 2, 2, 0, 1, 0, 0, 1, 0]);
+        assert_eq!(leading_digit_distribution(&[0, 900, u128::MAX]), [0, 0, 1, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_stream_fibonacci_to_buffer() {
+        let mut buffer = Vec::new();
+        stream_fibonacci_to(&mut buffer, 187).unwrap();
+        let lines: Vec<u128> = String::from_utf8(buffer).unwrap().lines().map(|line| line.parse().unwrap()).collect();
+        assert_eq!(lines.len(), 187);
+        assert_eq!(lines[..10], [0, 1, 1, 2, 3, 5, 8, 13, 21, 34]);
+        assert_eq!(lines.last().copied(), fib_nth(186));
+    }
+
+    #[test]
+    fn test_stream_fibonacci_to_stops_past_u128() {
+        let mut buffer = Vec::new();
+        let err = stream_fibonacci_to(&mut buffer, 188).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(String::from_utf8(buffer).unwrap().lines().count(), 187);
+    }
+
+    #[test]
+    fn test_paint_wraps_in_escape_codes() {
+        assert_eq!(paint("55", 33, true), "\x1b[33m55\x1b[0m");
+        assert_eq!(paint("55", 33, false), "55");
+    }
+
+    #[test]
+    fn test_no_color_disables_colors() {
+        assert!(color_allowed(false, true));
+        assert!(!color_allowed(true, true));
+        assert!(!color_allowed(false, false));
+    }
+
+    #[test]
+    fn test_fibonacci_backoff() {
+        assert_eq!(fibonacci_backoff(5, 100), vec![100, 100, 200, 300, 500]);
+        assert!(fibonacci_backoff(0, 100).is_empty());
+    }
+
+    #[test]
+    fn test_fibonacci_backoff_saturates() {
+        let delays = fibonacci_backoff(100, u64::MAX / 2);
+        assert_eq!(delays.len(), 100);
+        assert_eq!(*delays.last().unwrap(), u64::MAX);
     }
 }
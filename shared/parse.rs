@@ -0,0 +1,72 @@
+// Input parsers shared by the crates' prompts. Each crate pulls this file in with
+// `#[path = "../../shared/parse.rs"] mod parse;` since there is no common library crate.
+
+// Parse a finite number of either sign (NaN and inf are rejected), ignoring surrounding whitespace
+pub fn parse_finite_f64(input: &str) -> Result<f64, String> {
+    let trimmed = input.trim();
+    let value: f64 = trimmed
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", trimmed))?;
+    if !value.is_finite() {
+        return Err(format!("'{}' is not a finite number", trimmed));
+    }
+    Ok(value)
+}
+
+// Parse a strictly positive, finite number, ignoring surrounding whitespace
+pub fn parse_positive_f64(input: &str) -> Result<f64, String> {
+    let trimmed = input.trim();
+    let value = parse_finite_f64(trimmed)?;
+    if value <= 0.0 {
+        return Err(format!("'{}' must be greater than zero", trimmed));
+    }
+    Ok(value)
+}
+
+// Parse a strictly positive whole number, ignoring surrounding whitespace
+pub fn parse_positive_u64(input: &str) -> Result<u64, String> {
+    let trimmed = input.trim();
+    match trimmed.parse::<u64>() {
+        Ok(0) => Err(format!("'{}' must be greater than zero", trimmed)),
+        Ok(value) => Ok(value),
+        Err(_) if trimmed.starts_with('-') && trimmed[1..].parse::<u64>().is_ok() => {
+            Err(format!("'{}' must be greater than zero", trimmed))
+        }
+        Err(_) => Err(format!("'{}' is not a whole number", trimmed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_positive_f64() {
+        assert_eq!(parse_positive_f64("1.5"), Ok(1.5));
+        assert_eq!(parse_positive_f64(" 3 "), Ok(3.0));
+        assert_eq!(parse_positive_f64("-2"), Err("'-2' must be greater than zero".to_string()));
+        assert_eq!(parse_positive_f64("abc"), Err("'abc' is not a number".to_string()));
+        assert_eq!(parse_positive_f64("inf"), Err("'inf' is not a finite number".to_string()));
+        assert!(parse_positive_f64("NaN").is_err());
+        assert!(parse_positive_f64("0").is_err());
+    }
+
+    #[test]
+    fn test_parse_finite_f64() {
+        assert_eq!(parse_finite_f64(" -2.5 "), Ok(-2.5));
+        assert_eq!(parse_finite_f64("0"), Ok(0.0));
+        assert_eq!(parse_finite_f64("NaN"), Err("'NaN' is not a finite number".to_string()));
+        assert_eq!(parse_finite_f64("-inf"), Err("'-inf' is not a finite number".to_string()));
+        assert_eq!(parse_finite_f64("abc"), Err("'abc' is not a number".to_string()));
+    }
+
+    #[test]
+    fn test_parse_positive_u64() {
+        assert_eq!(parse_positive_u64(" 3 "), Ok(3));
+        assert_eq!(parse_positive_u64("-2"), Err("'-2' must be greater than zero".to_string()));
+        assert_eq!(parse_positive_u64("abc"), Err("'abc' is not a whole number".to_string()));
+        assert_eq!(parse_positive_u64("inf"), Err("'inf' is not a whole number".to_string()));
+        assert_eq!(parse_positive_u64("1.5"), Err("'1.5' is not a whole number".to_string()));
+        assert!(parse_positive_u64("0").is_err());
+    }
+}
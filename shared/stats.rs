@@ -0,0 +1,44 @@
+// Summary statistics shared by the crates. Pulled in with
+// `#[path = "../../shared/stats.rs"] mod stats;` like shared/parse.rs.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+// Count, sum, min, max and mean in a single pass - all zero for empty input
+pub fn summarize<I: IntoIterator<Item = f64>>(values: I) -> Summary {
+    let mut summary = Summary { count: 0, sum: 0.0, min: f64::INFINITY, max: f64::NEG_INFINITY, mean: 0.0 };
+    for value in values {
+        summary.count += 1;
+        summary.sum += value;
+        summary.min = summary.min.min(value);
+        summary.max = summary.max.max(value);
+    }
+
+    if summary.count == 0 {
+        return Summary { count: 0, sum: 0.0, min: 0.0, max: 0.0, mean: 0.0 };
+    }
+    summary.mean = summary.sum / summary.count as f64;
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_empty() {
+        assert_eq!(summarize(Vec::new()), Summary { count: 0, sum: 0.0, min: 0.0, max: 0.0, mean: 0.0 });
+    }
+
+    #[test]
+    fn test_summarize_three_values() {
+        let summary = summarize([4.0, -1.0, 6.0]);
+        assert_eq!(summary, Summary { count: 3, sum: 9.0, min: -1.0, max: 6.0, mean: 3.0 });
+    }
+}
@@ -0,0 +1,214 @@
+// Ties the order book to a cash wallet so buy orders are paid for up front.
+// crypto_wallet is a separate binary crate (shared/ only holds small helpers),
+// so this keeps a minimal quote-currency wallet modelled on its Wallet.
+use crate::{format_order_id, now_nanos, Order, OrderBook, OrderType};
+use std::collections::HashMap;
+
+// Cash held back for one open funded buy: what its unfilled amount costs at its limit price
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reservation {
+    pub price: f64,
+    pub held: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wallet {
+    pub id: String,
+    pub balance: f64,
+    // Open funded buys by order id
+    pub reserved: HashMap<String, Reservation>,
+    // How much of the book's trade log settle_funded_buys has already applied
+    pub settled_trades: usize,
+}
+
+impl Wallet {
+    pub fn new(id: &str, balance: f64) -> Wallet {
+        Wallet { id: id.to_string(), balance, reserved: HashMap::new(), settled_trades: 0 }
+    }
+
+    pub fn debit(&mut self, amount: f64) -> Result<(), String> {
+        if self.balance < amount {
+            return Err(format!("Insufficient balance! Have: ${:.2}, Need: ${:.2}", self.balance, amount));
+        }
+        self.balance -= amount;
+        Ok(())
+    }
+
+    pub fn credit(&mut self, amount: f64) {
+        self.balance += amount;
+    }
+}
+
+// Reserve `amount * price` from the wallet, then add the buy order and return its id.
+// Nothing is debited if the order is invalid, and the book is untouched if funds are short.
+// The order is marked funded, so modify_order won't let it drift from its reserve.
+pub fn place_funded_buy(book: &mut OrderBook, wallet: &mut Wallet, amount: f64, price: f64) -> Result<String, String> {
+    book.validate_order(amount, price).map_err(|e| e.to_string())?;
+    wallet.debit(amount * price)?;
+
+    let order = Order {
+        id: book.next_id,
+        order_type: OrderType::Buy,
+        amount,
+        price,
+        timestamp: now_nanos(),
+        owner: wallet.id.clone(),
+        funded: true,
+    };
+    book.next_id += 1;
+    let id = book.place_order(order);
+    wallet.reserved.insert(id.clone(), Reservation { price, held: amount * price });
+    Ok(id)
+}
+
+// Bring the wallet up to date with the book and return what was credited back. Each new
+// fill of a funded buy releases its cost at the limit price and refunds any price
+// improvement; a funded buy no longer resting (filled, or removed by any cancel path)
+// refunds whatever is still held.
+pub fn settle_funded_buys(book: &OrderBook, wallet: &mut Wallet) -> f64 {
+    let mut credited = 0.0;
+    let new_trades = book.trade_log().get(wallet.settled_trades..).unwrap_or_default();
+    for trade in new_trades {
+        if let Some(reservation) = wallet.reserved.get_mut(&format_order_id(&OrderType::Buy, trade.buy_id)) {
+            reservation.held -= trade.amount * reservation.price;
+            credited += trade.amount * (reservation.price - trade.price);
+        }
+    }
+    wallet.settled_trades = book.trade_log().len();
+
+    let gone: Vec<String> = wallet.reserved.keys().filter(|id| book.find_order_by_id(id).is_none()).cloned().collect();
+    for id in gone {
+        if let Some(reservation) = wallet.reserved.remove(&id) {
+            credited += reservation.held.max(0.0);
+        }
+    }
+
+    wallet.credit(credited);
+    credited
+}
+
+// Cancel a funded buy and refund whatever is still reserved for its unfilled amount,
+// along with any price improvement from earlier fills not yet settled
+pub fn cancel_funded_buy(book: &mut OrderBook, wallet: &mut Wallet, id: &str) -> Result<f64, String> {
+    match book.find_order_by_id(id) {
+        Some(order) if order.funded && order.owner == wallet.id => {}
+        _ => return Err(format!("no funded buy with id {} for wallet {}", id, wallet.id)),
+    }
+
+    book.cancel_order(id).map_err(|e| e.to_string())?;
+    Ok(settle_funded_buys(book, wallet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_funded_buy_debits_wallet() {
+        let mut book = OrderBook::new();
+        let mut wallet = Wallet::new("alice", 1_000.0);
+
+        let id = place_funded_buy(&mut book, &mut wallet, 10.0, 50.0).unwrap();
+        assert_eq!(wallet.balance, 500.0);
+        let order = book.find_order_by_id(&id).unwrap();
+        assert_eq!(order.owner, "alice");
+        assert_eq!(order.amount, 10.0);
+    }
+
+    #[test]
+    fn test_underfunded_buy_leaves_book_untouched() {
+        let mut book = OrderBook::new();
+        let mut wallet = Wallet::new("alice", 100.0);
+
+        assert!(place_funded_buy(&mut book, &mut wallet, 10.0, 50.0).is_err());
+        assert_eq!(wallet.balance, 100.0);
+        assert_eq!(book.total_orders(), 0);
+        assert_eq!(book.next_id, 1);
+    }
+
+    #[test]
+    fn test_off_tick_buy_is_not_debited() {
+        let mut book = OrderBook::new();
+        book.set_tick_size(0.05).unwrap();
+        let mut wallet = Wallet::new("alice", 1_000.0);
+
+        assert!(place_funded_buy(&mut book, &mut wallet, 1.0, 50.23).is_err());
+        assert_eq!(wallet.balance, 1_000.0);
+    }
+
+    #[test]
+    fn test_cancel_funded_buy_refunds_wallet() {
+        let mut book = OrderBook::new();
+        let mut wallet = Wallet::new("alice", 1_000.0);
+        let id = place_funded_buy(&mut book, &mut wallet, 10.0, 50.0).unwrap();
+
+        assert_eq!(cancel_funded_buy(&mut book, &mut wallet, &id), Ok(500.0));
+        assert_eq!(wallet.balance, 1_000.0);
+        assert_eq!(book.total_orders(), 0);
+        assert!(cancel_funded_buy(&mut book, &mut wallet, &id).is_err());
+        assert!(wallet.reserved.is_empty());
+    }
+
+    #[test]
+    fn test_reset_clear_cannot_reuse_a_funded_id() {
+        let mut book = OrderBook::new();
+        let mut wallet = Wallet::new("alice", 1_000.0);
+        let id = place_funded_buy(&mut book, &mut wallet, 10.0, 50.0).unwrap();
+
+        assert_eq!(book.clear(true), Err(crate::OrderError::IdsInUse));
+        assert_eq!(book.total_orders(), 1);
+
+        // Still refused once alice's order is gone: her wallet may not have settled it yet
+        book.clear(false).unwrap();
+        assert_eq!(book.clear(true), Err(crate::OrderError::IdsInUse));
+
+        // So bob's next buy can't take alice's id and be charged against her reservation
+        let bob_id = book.add_order_for("bob", OrderType::Buy, 5.0, 50.0).unwrap();
+        assert_ne!(bob_id, id);
+        book.add_order_for("carol", OrderType::Sell, 5.0, 50.0).unwrap();
+        book.match_orders();
+        assert_eq!(settle_funded_buys(&book, &mut wallet), 500.0);
+        assert_eq!(wallet.balance, 1_000.0);
+        assert!(wallet.reserved.is_empty());
+    }
+
+    #[test]
+    fn test_fill_below_limit_refunds_price_improvement() {
+        let mut book = OrderBook::new();
+        let mut wallet = Wallet::new("alice", 1_000.0);
+        book.add_order(OrderType::Sell, 4.0, 45.0).unwrap();
+        let id = place_funded_buy(&mut book, &mut wallet, 10.0, 50.0).unwrap();
+        book.match_orders();
+
+        // 4 bought at 45 against 50 reserved: $20 back now, the unfilled 6 @ 50 on cancel
+        assert_eq!(settle_funded_buys(&book, &mut wallet), 20.0);
+        assert_eq!(settle_funded_buys(&book, &mut wallet), 0.0);
+        assert_eq!(cancel_funded_buy(&mut book, &mut wallet, &id), Ok(300.0));
+        assert_eq!(wallet.balance, 1_000.0 - 4.0 * 45.0);
+    }
+
+    #[test]
+    fn test_funded_buy_removed_by_bulk_cancel_is_refunded() {
+        let mut book = OrderBook::new();
+        let mut wallet = Wallet::new("alice", 1_000.0);
+        place_funded_buy(&mut book, &mut wallet, 10.0, 50.0).unwrap();
+
+        book.cancel_at_price(&OrderType::Buy, 50.0, 1e-9);
+        assert_eq!(settle_funded_buys(&book, &mut wallet), 500.0);
+        assert_eq!(wallet.balance, 1_000.0);
+    }
+
+    #[test]
+    fn test_funded_buy_cannot_be_amended() {
+        let mut book = OrderBook::new();
+        let mut wallet = Wallet::new("alice", 1_000.0);
+        let id = place_funded_buy(&mut book, &mut wallet, 10.0, 50.0).unwrap();
+        let plain = book.add_order_for("alice", OrderType::Buy, 10.0, 50.0).unwrap();
+
+        assert_eq!(book.modify_order(&id, Some(20.0), None), Err(crate::OrderError::FundedOrder));
+        assert_eq!(book.modify_order(&id, None, Some(40.0)), Err(crate::OrderError::FundedOrder));
+        assert!(book.modify_order(&plain, Some(20.0), None).is_ok());
+        // An unfunded order can't be cancelled as a funded one either
+        assert!(cancel_funded_buy(&mut book, &mut wallet, &plain).is_err());
+    }
+}
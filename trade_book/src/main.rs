@@ -1,22 +1,274 @@
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod funding;
+#[path = "../../shared/parse.rs"]
+#[allow(dead_code)]
+mod parse;
+use parse::{parse_positive_f64, parse_positive_u64};
+#[path = "../../shared/stats.rs"]
+#[allow(dead_code)]
+mod stats;
+use stats::summarize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 enum OrderType {
     Buy,
     Sell,
 }
 
-#[derive(Debug, Clone)]
+// Reasons an order or book operation can be rejected
+#[derive(Debug, Clone, PartialEq)]
+enum OrderError {
+    NonPositiveAmount,
+    NonPositivePrice,
+    NotFinite,
+    InvalidTick,
+    InvalidLot,
+    OrderNotFound(String),
+    InsufficientLiquidity,
+    FundedOrder,
+    IdsInUse,
+    NonPositiveSize,
+}
+
+impl std::fmt::Display for OrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OrderError::NonPositiveAmount => write!(f, "amount must be greater than zero"),
+            OrderError::NonPositivePrice => write!(f, "price must be greater than zero"),
+            OrderError::NotFinite => write!(f, "amount and price must be finite numbers"),
+            OrderError::InvalidTick => write!(f, "price must be a multiple of the tick size"),
+            OrderError::InvalidLot => write!(f, "amount must be a whole number of lots"),
+            OrderError::OrderNotFound(id) => write!(f, "no order with id {}", id),
+            OrderError::InsufficientLiquidity => write!(f, "could not fully fill"),
+            OrderError::FundedOrder => write!(f, "funded orders can't be amended - cancel and place again"),
+            OrderError::IdsInUse => write!(f, "ids can't be reset once funded orders have been placed"),
+            OrderError::NonPositiveSize => write!(f, "tick and lot sizes must be greater than zero"),
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
+
+impl OrderError {
+    // Unknown id, reported the way ids are displayed (trimmed, upper-case) whichever mutator saw it
+    fn not_found(id: &str) -> OrderError {
+        OrderError::OrderNotFound(id.trim().to_uppercase())
+    }
+}
+
+// Note that == (and Hash) compare side, owner, amount and price only, so two distinct
+// orders can be equal - compare ids to tell orders apart. See the PartialEq impl below.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Order {
+    // Sequence number from the book's counter - shown to users as a side-prefixed id, see order_id
     id: u32,
     order_type: OrderType,
     amount: f64,
     price: f64,
+    timestamp: u128,
+    // Who placed the order; empty for anonymous orders
+    #[serde(default)]
+    owner: String,
+    // Paid for up front by a funding wallet (see funding.rs), which modify_order refuses
+    #[serde(default)]
+    funded: bool,
+}
+
+// Two orders are duplicates when side, owner, amount and price match - the id and
+// timestamp are ignored, since every order gets its own. f64 fields are compared by
+// bit pattern so Eq and Hash agree: 0.0 and -0.0 differ, and 0.1 + 0.2 won't equal 0.3.
+// NaN never reaches the book because add_order rejects it.
+impl PartialEq for Order {
+    fn eq(&self, other: &Self) -> bool {
+        self.order_type == other.order_type
+            && self.owner == other.owner
+            && self.amount.to_bits() == other.amount.to_bits()
+            && self.price.to_bits() == other.price.to_bits()
+    }
+}
+
+impl Eq for Order {}
+
+impl Order {
+    // Self-describing id such as "B-1" or "S-2"
+    fn order_id(&self) -> String {
+        format_order_id(&self.order_type, self.id)
+    }
+}
+
+impl std::fmt::Display for Order {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ID: {} | Type: {:?} | Amount: {:.2} | Price: ${:.2}",
+               self.order_id(), self.order_type, self.amount, self.price)?;
+        if !self.owner.is_empty() {
+            write!(f, " | Owner: {}", self.owner)?;
+        }
+        Ok(())
+    }
+}
+
+impl Hash for Order {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.order_type.hash(state);
+        self.owner.hash(state);
+        self.amount.to_bits().hash(state);
+        self.price.to_bits().hash(state);
+    }
+}
+
+// A conditional order held off the book until the last trade price reaches `trigger`,
+// then placed as a limit order at `limit`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StopOrder {
+    id: u32,
+    side: OrderType,
+    trigger: f64,
+    limit: f64,
+    amount: f64,
+}
+
+// A fill produced when a buy and a sell order cross
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Trade {
+    buy_id: u32,
+    sell_id: u32,
+    amount: f64,
+    price: f64,
+    // Charged to the resting (maker) and incoming (taker) order, in quote currency
+    #[serde(default)]
+    maker_fee: f64,
+    #[serde(default)]
+    taker_fee: f64,
+}
+
+impl Trade {
+    // The incoming order took liquidity, and orders always arrive with a higher id than
+    // anything already resting - market orders included
+    fn taker_side(&self) -> OrderType {
+        if self.buy_id > self.sell_id { OrderType::Buy } else { OrderType::Sell }
+    }
+}
+
+// How match_orders ranks resting orders at the same price
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+enum MatchPolicy {
+    // Earliest order first
+    #[default]
+    PriceTime,
+    // Largest amount first, then earliest
+    PriceSize,
+}
+
+// Fees in basis points of each trade's notional (amount * price)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+struct FeeSchedule {
+    maker_bps: u32,
+    taker_bps: u32,
+}
+
+impl FeeSchedule {
+    // (maker_fee, taker_fee) on a fill of `amount` at `price`
+    fn fees(&self, amount: f64, price: f64) -> (f64, f64) {
+        let notional = amount * price;
+        (notional * self.maker_bps as f64 / 10_000.0, notional * self.taker_bps as f64 / 10_000.0)
+    }
+}
+
+// One entry in the book's append-only log - replaying the log rebuilds the book
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum BookEvent {
+    Added(Order),
+    // Side-prefixed id of the cancelled order, e.g. "B-1"
+    Canceled(String),
+    Matched(Trade),
+    // New amount for an order amended in place (same price, so it keeps its priority)
+    Amended(String, f64),
+    // A stop order put on hold, and later (by its side-prefixed id) released by check_stops
+    StopAdded(StopOrder),
+    StopFired(String),
+    // Every setting after one of them changed
+    Configured { tick_size: f64, min_lot: f64, fee_schedule: FeeSchedule, match_policy: MatchPolicy },
 }
 
-#[derive(Debug)]
+// Combined counts, volumes (summed amount) and values (summed price * amount)
+#[derive(Debug, Clone, PartialEq)]
+struct BookStats {
+    buy_count: usize,
+    sell_count: usize,
+    buy_volume: f64,
+    sell_volume: f64,
+    buy_value: f64,
+    sell_value: f64,
+}
+
+// Choices offered by the interactive menu
+#[derive(Debug, PartialEq)]
+enum MenuChoice {
+    AddBuy,
+    AddSell,
+    ViewBook,
+    CancelOrder,
+    Exit,
+    Invalid(String),
+}
+
+impl MenuChoice {
+    fn from_input(input: &str) -> MenuChoice {
+        match input.trim() {
+            "1" => MenuChoice::AddBuy,
+            "2" => MenuChoice::AddSell,
+            "3" => MenuChoice::ViewBook,
+            "4" => MenuChoice::CancelOrder,
+            "5" => MenuChoice::Exit,
+            invalid => MenuChoice::Invalid(invalid.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct OrderBook {
     buy_orders: Vec<Order>,
     sell_orders: Vec<Order>,
     next_id: u32,
+    // Prices must be whole multiples of this; zero or less turns the check off
+    #[serde(default = "default_tick_size")]
+    tick_size: f64,
+    // Amounts must be whole multiples of this, so at least one lot; zero (the default) turns it off
+    #[serde(default)]
+    min_lot: f64,
+    // Fees attached to trades from match_orders and market orders - free by default
+    #[serde(default)]
+    fee_schedule: FeeSchedule,
+    #[serde(default)]
+    match_policy: MatchPolicy,
+    // Every trade executed by match_orders and market orders, oldest first
+    #[serde(default)]
+    trades: Vec<Trade>,
+    // Price of the most recent trade, which stop orders trigger on
+    #[serde(default)]
+    last_trade_price: Option<f64>,
+    // Stop orders waiting for their trigger
+    #[serde(default)]
+    stops: Vec<StopOrder>,
+    // Every add, cancel, amendment and fill in the order it happened
+    #[serde(default)]
+    events: Vec<BookEvent>,
+    // Running sums of amount * price and amount per side, so vwap is O(1).
+    // Not persisted - they're rebuilt from the orders on load.
+    #[serde(skip)]
+    buy_notional: f64,
+    #[serde(skip)]
+    buy_volume: f64,
+    #[serde(skip)]
+    sell_notional: f64,
+    #[serde(skip)]
+    sell_volume: f64,
 }
 
 impl OrderBook {
@@ -25,23 +277,248 @@ impl OrderBook {
             buy_orders: Vec::new(),
             sell_orders: Vec::new(),
             next_id: 1,
+            tick_size: default_tick_size(),
+            min_lot: 0.0,
+            fee_schedule: FeeSchedule::default(),
+            match_policy: MatchPolicy::default(),
+            trades: Vec::new(),
+            last_trade_price: None,
+            stops: Vec::new(),
+            events: Vec::new(),
+            buy_notional: 0.0,
+            buy_volume: 0.0,
+            sell_notional: 0.0,
+            sell_volume: 0.0,
+        }
+    }
+
+    // Add (positive) or remove (negative) volume and notional from one side's running totals.
+    // A side with no orders left is reset to exactly zero so rounding error can't build up.
+    fn adjust_totals(&mut self, side: &OrderType, volume: f64, notional: f64) {
+        let (orders, side_volume, side_notional) = match side {
+            OrderType::Buy => (&self.buy_orders, &mut self.buy_volume, &mut self.buy_notional),
+            OrderType::Sell => (&self.sell_orders, &mut self.sell_volume, &mut self.sell_notional),
+        };
+        if orders.is_empty() {
+            *side_volume = 0.0;
+            *side_notional = 0.0;
+        } else {
+            *side_volume += volume;
+            *side_notional += notional;
+        }
+    }
+
+    // Rebuild the running totals with a full scan, after bulk changes
+    fn recompute_totals(&mut self) {
+        self.buy_volume = self.get_total_volume_by_type(&OrderType::Buy);
+        self.buy_notional = self.get_total_value_by_type(&OrderType::Buy);
+        self.sell_volume = self.get_total_volume_by_type(&OrderType::Sell);
+        self.sell_notional = self.get_total_value_by_type(&OrderType::Sell);
+    }
+
+    // Independent deep copy for read-only use elsewhere (e.g. a reporting thread).
+    // Order ids and the id counter are preserved, so the copy reports the same ids.
+    fn snapshot(&self) -> OrderBook {
+        self.clone()
+    }
+
+    // Write every order and the id counter to a JSON file
+    fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    // Restore a saved book - a missing file yields a fresh, empty book
+    fn load(path: &str) -> io::Result<OrderBook> {
+        match fs::read_to_string(path) {
+            Ok(json) => {
+                let mut book: OrderBook = serde_json::from_str(&json).map_err(io::Error::other)?;
+                book.recompute_totals();
+                Ok(book)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(OrderBook::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Tick and lot sizes must be finite and positive; a rejected size leaves the old one in place
+    fn set_tick_size(&mut self, tick: f64) -> Result<(), OrderError> {
+        Self::validate_size(tick)?;
+        self.tick_size = tick;
+        self.log_settings();
+        Ok(())
+    }
+
+    fn set_min_lot(&mut self, lot: f64) -> Result<(), OrderError> {
+        Self::validate_size(lot)?;
+        self.min_lot = lot;
+        self.log_settings();
+        Ok(())
+    }
+
+    fn validate_size(size: f64) -> Result<(), OrderError> {
+        if !size.is_finite() {
+            return Err(OrderError::NotFinite);
+        }
+        if size <= 0.0 {
+            return Err(OrderError::NonPositiveSize);
         }
+        Ok(())
     }
 
-    fn add_order(&mut self, order_type: OrderType, amount: f64, price: f64) {
+    fn set_fee_schedule(&mut self, fee_schedule: FeeSchedule) {
+        self.fee_schedule = fee_schedule;
+        self.log_settings();
+    }
+
+    fn set_match_policy(&mut self, policy: MatchPolicy) {
+        self.match_policy = policy;
+        self.log_settings();
+    }
+
+    // Record the book's settings after a setter changed one, so replay can restore them
+    fn log_settings(&mut self) {
+        self.events.push(BookEvent::Configured {
+            tick_size: self.tick_size,
+            min_lot: self.min_lot,
+            fee_schedule: self.fee_schedule,
+            match_policy: self.match_policy,
+        });
+    }
+
+    // Validate and insert a new anonymous order, returning its id
+    fn add_order(&mut self, order_type: OrderType, amount: f64, price: f64) -> Result<String, OrderError> {
+        self.add_order_for("", order_type, amount, price)
+    }
+
+    // Validate and insert a new order placed by `owner`, returning its id
+    fn add_order_for(&mut self, owner: &str, order_type: OrderType, amount: f64, price: f64) -> Result<String, OrderError> {
+        self.validate_order(amount, price)?;
+
         let order = Order {
             id: self.next_id,
-            order_type: order_type.clone(),
+            order_type,
             amount,
             price,
+            timestamp: now_nanos(),
+            owner: owner.to_string(),
+            funded: false,
         };
 
-        match order_type {
-            OrderType::Buy => self.buy_orders.push(order),
-            OrderType::Sell => self.sell_orders.push(order),
-        }
+        self.next_id += 1;
+        Ok(self.place_order(order))
+    }
+
+    // Log and insert an order that has already been validated, returning its id
+    fn place_order(&mut self, order: Order) -> String {
+        let id = order.order_id();
+        self.events.push(BookEvent::Added(order.clone()));
+        self.insert_order(order);
+        id
+    }
+
+    // Hold a stop order until the last trade price reaches `trigger`. The amount and
+    // limit price are validated now. The returned id names the pending stop; the live
+    // order gets a fresh id when it fires, see check_stops.
+    fn add_stop(&mut self, side: OrderType, trigger: f64, limit: f64, amount: f64) -> Result<String, OrderError> {
+        self.validate_order(amount, limit)?;
+        Self::validate(amount, trigger)?;
 
+        let id = self.next_id;
         self.next_id += 1;
+        let order_id = format_order_id(&side, id);
+        let stop = StopOrder { id, side, trigger, limit, amount };
+        self.events.push(BookEvent::StopAdded(stop.clone()));
+        self.stops.push(stop);
+        Ok(order_id)
+    }
+
+    // Place every stop the last trade price has reached - buy stops at or above their
+    // trigger, sell stops at or below - as live limit orders, and return their ids.
+    // Each gets the next id on firing, so it arrives after (and takes liquidity from)
+    // anything placed while it waited. Call match_orders afterwards to trade any that cross.
+    fn check_stops(&mut self) -> Vec<String> {
+        let Some(last) = self.last_trade_price else {
+            return Vec::new();
+        };
+        let (fired, pending): (Vec<StopOrder>, Vec<StopOrder>) =
+            self.stops.drain(..).partition(|stop| match stop.side {
+                OrderType::Buy => last >= stop.trigger,
+                OrderType::Sell => last <= stop.trigger,
+            });
+        self.stops = pending;
+
+        fired
+            .into_iter()
+            .map(|stop| {
+                self.events.push(BookEvent::StopFired(format_order_id(&stop.side, stop.id)));
+                let id = self.next_id;
+                self.next_id += 1;
+                self.place_order(Order {
+                    id,
+                    order_type: stop.side,
+                    amount: stop.amount,
+                    price: stop.limit,
+                    timestamp: now_nanos(),
+                    owner: String::new(),
+                    funded: false,
+                })
+            })
+            .collect()
+    }
+
+    // Add several orders at once. Every entry is validated before any is inserted,
+    // so one bad entry leaves the book unchanged. Ids are returned in input order.
+    fn add_orders(&mut self, orders: &[(OrderType, f64, f64)]) -> Result<Vec<String>, OrderError> {
+        for (_, amount, price) in orders {
+            self.validate_order(*amount, *price)?;
+        }
+        orders
+            .iter()
+            .map(|(order_type, amount, price)| self.add_order(order_type.clone(), *amount, *price))
+            .collect()
+    }
+
+    // Everything validate checks, plus the book's own tick and lot sizes
+    fn validate_order(&self, amount: f64, price: f64) -> Result<(), OrderError> {
+        Self::validate(amount, price)?;
+        if self.tick_size > 0.0 && !is_multiple_of(price, self.tick_size) {
+            return Err(OrderError::InvalidTick);
+        }
+        if self.min_lot > 0.0 && (amount < self.min_lot || !is_multiple_of(amount, self.min_lot)) {
+            return Err(OrderError::InvalidLot);
+        }
+        Ok(())
+    }
+
+    fn validate(amount: f64, price: f64) -> Result<(), OrderError> {
+        if !amount.is_finite() || !price.is_finite() {
+            return Err(OrderError::NotFinite);
+        }
+        if amount <= 0.0 {
+            return Err(OrderError::NonPositiveAmount);
+        }
+        if price <= 0.0 {
+            return Err(OrderError::NonPositivePrice);
+        }
+        Ok(())
+    }
+
+    // Keep each side in price-time priority: bids high-to-low, asks low-to-high,
+    // and an order goes behind every resting order at the same price.
+    fn insert_order(&mut self, order: Order) {
+        let (side, amount, price) = (order.order_type.clone(), order.amount, order.price);
+        match order.order_type {
+            OrderType::Buy => {
+                let pos = self.buy_orders.partition_point(|o| o.price >= order.price);
+                self.buy_orders.insert(pos, order);
+            }
+            OrderType::Sell => {
+                let pos = self.sell_orders.partition_point(|o| o.price <= order.price);
+                self.sell_orders.insert(pos, order);
+            }
+        }
+        self.adjust_totals(&side, amount, amount * price);
     }
 
     fn show_order_book(&self) {
@@ -51,24 +528,59 @@ impl OrderBook {
         if self.buy_orders.is_empty() {
             println!("  No buy orders");
         } else {
-            for order in &self.buy_orders {
-                println!("  ID: {} | Type: {:?} | Amount: {:.2} | Price: ${:.2}",
-                         order.id, order.order_type, order.amount, order.price);
-            }
+            print!("{}", render_table(&self.buy_orders));
         }
 
         println!("\n SELL ORDERS:");
         if self.sell_orders.is_empty() {
             println!("  No sell orders");
         } else {
-            for order in &self.sell_orders {
-                println!("  ID: {} | Type: {:?} | Amount: {:.2} | Price: ${:.2}",
-                         order.id, order.order_type, order.amount, order.price);
-            }
+            print!("{}", render_table(&self.sell_orders));
         }
         println!("==================\n");
     }
 
+    // Aggregated (price, total_amount) levels - bids high-to-low, asks low-to-high
+    fn depth(&self, side: &OrderType) -> Vec<(f64, f64)> {
+        let mut levels: Vec<(f64, f64)> = Vec::new();
+        for order in self.get_orders_by_type(side) {
+            match levels.last_mut() {
+                Some((price, total)) if *price == order.price => *total += order.amount,
+                _ => levels.push((order.price, order.amount)),
+            }
+        }
+        levels
+    }
+
+    // (price, level_volume, cumulative_fraction) from the best price outward.
+    // The fraction of the side's total volume reaches 1.0 at the last level.
+    fn cumulative_depth(&self, side: &OrderType) -> Vec<(f64, f64, f64)> {
+        let levels = self.depth(side);
+        let total: f64 = levels.iter().map(|(_, volume)| volume).sum();
+
+        let mut running = 0.0;
+        levels
+            .into_iter()
+            .map(|(price, volume)| {
+                running += volume;
+                (price, volume, running / total)
+            })
+            .collect()
+    }
+
+    fn show_depth(&self) {
+        println!("=== MARKET DEPTH ===");
+        println!("{:<6} | {:>10} | {:>10} | {:>6}", "Side", "Price", "Amount", "Cum %");
+        println!("-----------------------------------------");
+        for (price, amount, fraction) in self.cumulative_depth(&OrderType::Sell).iter().rev() {
+            println!("{:<6} | {:>10} | {:>10.2} | {:>5.1}%", "ASK", format!("${:.2}", price), amount, fraction * 100.0);
+        }
+        for (price, amount, fraction) in self.cumulative_depth(&OrderType::Buy) {
+            println!("{:<6} | {:>10} | {:>10.2} | {:>5.1}%", "BID", format!("${:.2}", price), amount, fraction * 100.0);
+        }
+        println!("====================\n");
+    }
+
     fn total_orders(&self) -> usize {
         self.buy_orders.len() + self.sell_orders.len()
     }
@@ -80,74 +592,2073 @@ impl OrderBook {
         }
     }
 
-    fn find_order_by_id(&self, id: u32) -> Option<&Order> {
-        for order in &self.buy_orders {
-            if order.id == id {
-                return Some(order);
-            }
+    // Owned copy of one side sorted by price; orders at the same price keep their
+    // current relative order. The book itself is not touched.
+    fn sorted_orders(&self, side: &OrderType, by_price_desc: bool) -> Vec<Order> {
+        let mut orders = self.get_orders_by_type(side).clone();
+        if by_price_desc {
+            orders.sort_by(|a, b| b.price.total_cmp(&a.price));
+        } else {
+            orders.sort_by(|a, b| a.price.total_cmp(&b.price));
         }
-        for order in &self.sell_orders {
-            if order.id == id {
-                return Some(order);
-            }
+        orders
+    }
+
+    // Look up an order by its side-prefixed id, e.g. "B-1"
+    fn find_order_by_id(&self, id: &str) -> Option<&Order> {
+        let (side, seq) = parse_order_id(id).ok()?;
+        self.get_orders_by_type(&side).iter().find(|order| order.id == seq)
+    }
+
+    // Drop every order. With `reset_ids` the next order is id 1 again; otherwise
+    // ids keep increasing so references to earlier orders stay unique. Wallets key their
+    // reservations by id, so once a funded order has been placed the reset is refused
+    // and the book is left as it was.
+    fn clear(&mut self, reset_ids: bool) -> Result<(), OrderError> {
+        if reset_ids && self.events.iter().any(|event| matches!(event, BookEvent::Added(order) if order.funded)) {
+            return Err(OrderError::IdsInUse);
         }
-        None
+        let cleared: Vec<String> = self.buy_orders.iter().chain(&self.sell_orders).map(Order::order_id).collect();
+        self.log_cancels(cleared);
+        self.buy_orders.clear();
+        self.sell_orders.clear();
+        self.recompute_totals();
+        if reset_ids {
+            self.next_id = 1;
+        }
+        Ok(())
+    }
+
+    // Remove the order with this side-prefixed id and return it
+    fn cancel_order(&mut self, id: &str) -> Result<Order, OrderError> {
+        let not_found = || OrderError::not_found(id);
+        let (side, seq) = parse_order_id(id).map_err(|_| not_found())?;
+        let orders = match side {
+            OrderType::Buy => &mut self.buy_orders,
+            OrderType::Sell => &mut self.sell_orders,
+        };
+        let pos = orders.iter().position(|order| order.id == seq).ok_or_else(not_found)?;
+        let order = orders.remove(pos);
+        self.events.push(BookEvent::Canceled(order.order_id()));
+        self.adjust_totals(&order.order_type, -order.amount, -order.amount * order.price);
+        Ok(order)
+    }
+
+    // Remove every order on one side priced within `epsilon` of `price`; returns the count
+    fn cancel_at_price(&mut self, side: &OrderType, price: f64, epsilon: f64) -> usize {
+        let orders = match side {
+            OrderType::Buy => &mut self.buy_orders,
+            OrderType::Sell => &mut self.sell_orders,
+        };
+        let (removed, kept): (Vec<Order>, Vec<Order>) =
+            orders.drain(..).partition(|order| (order.price - price).abs() <= epsilon);
+        *orders = kept;
+        self.log_cancels(removed.iter().map(Order::order_id).collect());
+        self.recompute_totals();
+        removed.len()
+    }
+
+    // Record orders removed in bulk as individual cancels
+    fn log_cancels(&mut self, ids: Vec<String>) {
+        self.events.extend(ids.into_iter().map(BookEvent::Canceled));
+    }
+
+    // Amend an order and return its id. An amount change keeps the order's place and id.
+    // A price change makes it a new arrival: it gets the next id and goes to the back of
+    // its new price level, so if it now crosses it is the taker. Funded buys are refused,
+    // since their wallet reserve would no longer match.
+    fn modify_order(&mut self, id: &str, new_amount: Option<f64>, new_price: Option<f64>) -> Result<String, OrderError> {
+        let order = self.find_order_by_id(id).ok_or_else(|| OrderError::not_found(id))?;
+        if order.funded {
+            return Err(OrderError::FundedOrder);
+        }
+        let amount = new_amount.unwrap_or(order.amount);
+        let price = new_price.unwrap_or(order.price);
+        self.validate_order(amount, price)?;
+
+        if price == order.price {
+            let id = order.order_id();
+            self.amend_amount(&id, amount);
+            return Ok(id);
+        }
+        let mut order = self.cancel_order(id)?;
+        order.id = self.next_id;
+        order.amount = amount;
+        order.price = price;
+        order.timestamp = now_nanos();
+        self.next_id += 1;
+        Ok(self.place_order(order))
+    }
+
+    // Set an order's amount without touching its priority, keeping the running totals in step
+    fn amend_amount(&mut self, id: &str, amount: f64) {
+        let Ok((side, seq)) = parse_order_id(id) else {
+            return;
+        };
+        let orders = match side {
+            OrderType::Buy => &mut self.buy_orders,
+            OrderType::Sell => &mut self.sell_orders,
+        };
+        let Some(order) = orders.iter_mut().find(|order| order.id == seq) else {
+            return;
+        };
+        let (delta, price) = (amount - order.amount, order.price);
+        order.amount = amount;
+        self.adjust_totals(&side, delta, delta * price);
+        self.events.push(BookEvent::Amended(format_order_id(&side, seq), amount));
+    }
+
+    // All orders placed at or after time `t` (nanoseconds since epoch), across both sides
+    fn orders_since(&self, t: u128) -> Vec<&Order> {
+        self.filter_orders(|order| order.timestamp >= t)
+    }
+
+    // Drop duplicate orders from each side, keeping the one with the best time priority
+    fn dedup_orders(&mut self) {
+        let mut dropped = Vec::new();
+        for side in [&mut self.buy_orders, &mut self.sell_orders] {
+            let mut seen = HashSet::new();
+            side.retain(|order| {
+                let first = seen.insert(order.clone());
+                if !first {
+                    dropped.push(order.order_id());
+                }
+                first
+            });
+        }
+        self.log_cancels(dropped);
+        self.recompute_totals();
+    }
+
+    // Remove and return every order older than `max_age_nanos` as of `now`, from both sides
+    fn expire_older_than(&mut self, max_age_nanos: u128, now: u128) -> Vec<Order> {
+        let is_stale = |order: &Order| now.saturating_sub(order.timestamp) > max_age_nanos;
+
+        let mut expired = Vec::new();
+        for side in [&mut self.buy_orders, &mut self.sell_orders] {
+            let (stale, fresh): (Vec<Order>, Vec<Order>) = side.drain(..).partition(is_stale);
+            *side = fresh;
+            expired.extend(stale);
+        }
+        self.log_cancels(expired.iter().map(Order::order_id).collect());
+        self.recompute_totals();
+        expired
+    }
+
+    // All orders on either side (bids first) that satisfy the predicate
+    fn filter_orders<F: Fn(&Order) -> bool>(&self, pred: F) -> Vec<&Order> {
+        self.buy_orders
+            .iter()
+            .chain(self.sell_orders.iter())
+            .filter(|order| pred(order))
+            .collect()
     }
 
     fn get_total_value_by_type(&self, order_type: &OrderType) -> f64 {
         let orders = self.get_orders_by_type(order_type);
-        orders.iter()
-            .map(|order| order.amount * order.price)
+        summarize(orders.iter().map(|order| order.amount * order.price)).sum
+    }
+
+    // (buy_value - sell_value) / (buy_value + sell_value), in [-1, 1].
+    // Positive when more value rests on the bid side; None for an empty book.
+    fn notional_imbalance(&self) -> Option<f64> {
+        let buy_value = self.get_total_value_by_type(&OrderType::Buy);
+        let sell_value = self.get_total_value_by_type(&OrderType::Sell);
+        let total = buy_value + sell_value;
+        if total <= 0.0 {
+            return None;
+        }
+        Some((buy_value - sell_value) / total)
+    }
+
+    // Highest-priced buy order
+    fn best_bid(&self) -> Option<&Order> {
+        self.buy_orders.first()
+    }
+
+    // Lowest-priced sell order
+    fn best_ask(&self) -> Option<&Order> {
+        self.sell_orders.first()
+    }
+
+    // Gap between the best ask and the best bid, if both sides have orders
+    fn spread(&self) -> Option<f64> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some(ask.price - bid.price),
+            _ => None,
+        }
+    }
+
+    fn get_total_volume_by_type(&self, order_type: &OrderType) -> f64 {
+        self.get_orders_by_type(order_type)
+            .iter()
+            .map(|order| order.amount)
             .sum()
     }
-}
 
-fn main() {
-    println!(" Order Book System Demo\n");
+    fn book_stats(&self) -> BookStats {
+        BookStats {
+            buy_count: self.get_orders_by_type(&OrderType::Buy).len(),
+            sell_count: self.get_orders_by_type(&OrderType::Sell).len(),
+            buy_volume: self.get_total_volume_by_type(&OrderType::Buy),
+            sell_volume: self.get_total_volume_by_type(&OrderType::Sell),
+            buy_value: self.get_total_value_by_type(&OrderType::Buy),
+            sell_value: self.get_total_value_by_type(&OrderType::Sell),
+        }
+    }
 
-    let mut order_book = OrderBook::new();
+    // Midpoint between the best bid and the best ask
+    fn mid_price(&self) -> Option<f64> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some((bid.price + ask.price) / 2.0),
+            _ => None,
+        }
+    }
 
-    println!("Adding buy orders...");
-    order_book.add_order(OrderType::Buy, 100.0, 50.25);
-    order_book.add_order(OrderType::Buy, 200.0, 49.80);
-    order_book.add_order(OrderType::Buy, 150.0, 51.00);
+    // Value of one side after hypothetically matching away everything that crosses,
+    // i.e. the liquidity that would actually rest on the book. The book is not modified.
+    fn resting_value(&self, side: &OrderType) -> f64 {
+        let mut buys = self.buy_orders.clone();
+        let mut sells = self.sell_orders.clone();
 
-    println!("Adding sell orders...");
-    order_book.add_order(OrderType::Sell, 75.0, 52.50);
-    order_book.add_order(OrderType::Sell, 300.0, 53.20);
-    order_book.add_order(OrderType::Sell, 125.0, 51.75);
+        while let (Some(buy), Some(sell)) = (buys.first_mut(), sells.first_mut()) {
+            if buy.price < sell.price {
+                break;
+            }
+            let fill = buy.amount.min(sell.amount);
+            buy.amount -= fill;
+            sell.amount -= fill;
+            if buy.amount <= 0.0 {
+                buys.remove(0);
+            }
+            if sell.amount <= 0.0 {
+                sells.remove(0);
+            }
+        }
 
-    order_book.show_order_book();
+        let remaining = match side {
+            OrderType::Buy => &buys,
+            OrderType::Sell => &sells,
+        };
+        remaining.iter().map(|order| order.amount * order.price).sum()
+    }
 
-    println!(" Order Book Statistics:");
-    println!("Total orders: {}", order_book.total_orders());
-    println!("Buy orders: {}", order_book.buy_orders.len());
-    println!("Sell orders: {}", order_book.sell_orders.len());
+    // Proceeds from selling `amount` into the bids, best price first. Stops early
+    // if the bids run out, so the result reflects both price impact and thin liquidity.
+    fn liquidation_value(&self, amount: f64) -> f64 {
+        let mut remaining = amount.max(0.0);
+        let mut proceeds = 0.0;
+        for bid in &self.buy_orders {
+            if remaining <= 0.0 {
+                break;
+            }
+            let fill = remaining.min(bid.amount);
+            proceeds += fill * bid.price;
+            remaining -= fill;
+        }
+        proceeds
+    }
 
-    println!("\n Finding order by ID:");
-    if let Some(order) = order_book.find_order_by_id(3) {
-        println!("Found order ID 3: {:?} - Amount: {}, Price: ${}",
-                 order.order_type, order.amount, order.price);
+    // Volume-weighted average price over one side: sum(price * amount) / sum(amount)
+    fn vwap(&self, side: &OrderType) -> Option<f64> {
+        let (volume, notional) = match side {
+            OrderType::Buy => (self.buy_volume, self.buy_notional),
+            OrderType::Sell => (self.sell_volume, self.sell_notional),
+        };
+        if volume <= 0.0 {
+            return None;
+        }
+        Some(notional / volume)
     }
 
-    // Demonstrate total value calculations
-    let buy_total = order_book.get_total_value_by_type(&OrderType::Buy);
-    let sell_total = order_book.get_total_value_by_type(&OrderType::Sell);
-    println!("\n Total Values:");
-    println!("Buy orders total value: ${:.2}", buy_total);
-    println!("Sell orders total value: ${:.2}", sell_total);
+    // Call-auction uncross: pick the single price that maximises executable volume,
+    // where bids at or above it trade against asks at or below it. When several
+    // candidate prices tie, the midpoint of the tied range is used.
+    // Returns (clearing_price, matched_volume), or None if the book does not cross.
+    fn uncross(&self) -> Option<(f64, f64)> {
+        let mut best_volume = 0.0;
+        let mut tied_prices: Vec<f64> = Vec::new();
 
-    // Demonstrate immutable borrowing
-    let buy_orders_ref = order_book.get_orders_by_type(&OrderType::Buy);
-    println!("\n Buy orders via reference: {} orders", buy_orders_ref.len());
+        for candidate in self.buy_orders.iter().chain(self.sell_orders.iter()).map(|o| o.price) {
+            let demand: f64 = self.buy_orders.iter().filter(|o| o.price >= candidate).map(|o| o.amount).sum();
+            let supply: f64 = self.sell_orders.iter().filter(|o| o.price <= candidate).map(|o| o.amount).sum();
+            let volume = demand.min(supply);
 
-    // Demonstrate pattern matching with order types
-    let order_types = vec![OrderType::Buy, OrderType::Sell];
-    for ot in &order_types {
-        let count = order_book.get_orders_by_type(ot).len();
-        match ot {
-            OrderType::Buy => println!(" Buy orders count: {}", count),
-            OrderType::Sell => println!(" Sell orders count: {}", count),
+            if volume > best_volume {
+                best_volume = volume;
+                tied_prices = vec![candidate];
+            } else if volume == best_volume && volume > 0.0 {
+                tied_prices.push(candidate);
+            }
+        }
+
+        let low = tied_prices.iter().copied().reduce(f64::min)?;
+        let high = tied_prices.iter().copied().reduce(f64::max)?;
+        Some(((low + high) / 2.0, best_volume))
+    }
+
+    // Match crossing orders by price, then time or size per the match policy, until the book no longer crosses.
+    // Each trade executes at the resting (earlier) order's price. An owner's buy is
+    // never matched against their own sell - the next eligible counterparty is used instead.
+    fn match_orders(&mut self) -> Vec<Trade> {
+        let mut trades = Vec::new();
+
+        while let Some((b, s)) = self.next_match() {
+            let buy = &self.buy_orders[b];
+            let sell = &self.sell_orders[s];
+
+            let amount = buy.amount.min(sell.amount);
+            let price = if buy.id < sell.id { buy.price } else { sell.price };
+            let (maker_fee, taker_fee) = self.fee_schedule.fees(amount, price);
+            let trade = Trade { buy_id: buy.id, sell_id: sell.id, amount, price, maker_fee, taker_fee };
+
+            self.apply_fill(&trade);
+            trades.push(trade);
+        }
+
+        self.record_trades(&trades);
+        trades
+    }
+
+    // Log executed trades, add them to the ledger and move the last trade price
+    fn record_trades(&mut self, trades: &[Trade]) {
+        self.events.extend(trades.iter().cloned().map(BookEvent::Matched));
+        self.trades.extend(trades.iter().cloned());
+        if let Some(last) = trades.last() {
+            self.last_trade_price = Some(last.price);
+        }
+    }
+
+    fn trade_log(&self) -> &[Trade] {
+        &self.trades
+    }
+
+    // Realized P&L of the incoming (taker) side across the session, starting flat:
+    // every sell adds its notional and every buy subtracts it. Fees are not included.
+    fn session_pnl(&self) -> f64 {
+        self.trades
+            .iter()
+            .map(|trade| match trade.taker_side() {
+                OrderType::Buy => -trade.amount * trade.price,
+                OrderType::Sell => trade.amount * trade.price,
+            })
+            .sum()
+    }
+
+    // Take a trade's amount off the buy and sell orders it names, removing any that are used up.
+    // An id that isn't resting, like the incoming side of a market order, is skipped.
+    fn apply_fill(&mut self, trade: &Trade) {
+        for (side, seq) in [(OrderType::Buy, trade.buy_id), (OrderType::Sell, trade.sell_id)] {
+            let orders = match side {
+                OrderType::Buy => &mut self.buy_orders,
+                OrderType::Sell => &mut self.sell_orders,
+            };
+            let Some(pos) = orders.iter().position(|order| order.id == seq) else {
+                continue;
+            };
+            let price = orders[pos].price;
+            orders[pos].amount -= trade.amount;
+            if orders[pos].amount <= 0.0 {
+                orders.remove(pos);
+            }
+            self.adjust_totals(&side, -trade.amount, -trade.amount * price);
+        }
+    }
+
+    // Rebuild a book from an event log: orders, pending stops, the trade ledger and the
+    // settings. Orders keep their original ids and timestamps, and the id counter resumes
+    // after the highest id seen. A clear that reset the ids is not recorded, so after one
+    // the counter may end up higher than the live book's.
+    fn replay(events: &[BookEvent]) -> OrderBook {
+        let mut book = OrderBook::new();
+        for event in events {
+            match event {
+                BookEvent::Added(order) => {
+                    book.next_id = book.next_id.max(order.id + 1);
+                    book.insert_order(order.clone());
+                }
+                BookEvent::Canceled(id) => {
+                    let _ = book.cancel_order(id);
+                }
+                BookEvent::Matched(trade) => {
+                    book.next_id = book.next_id.max(trade.buy_id.max(trade.sell_id) + 1);
+                    book.apply_fill(trade);
+                    book.record_trades(std::slice::from_ref(trade));
+                }
+                BookEvent::Amended(id, amount) => book.amend_amount(id, *amount),
+                BookEvent::StopAdded(stop) => {
+                    book.next_id = book.next_id.max(stop.id + 1);
+                    book.stops.push(stop.clone());
+                }
+                BookEvent::StopFired(id) => book.stops.retain(|stop| format_order_id(&stop.side, stop.id) != *id),
+                BookEvent::Configured { tick_size, min_lot, fee_schedule, match_policy } => {
+                    book.tick_size = *tick_size;
+                    book.min_lot = *min_lot;
+                    book.fee_schedule = *fee_schedule;
+                    book.match_policy = *match_policy;
+                }
+            }
+        }
+        book.events = events.to_vec();
+        book
+    }
+
+    // Indexes of the highest-priority crossing buy/sell pair with different owners
+    fn next_match(&self) -> Option<(usize, usize)> {
+        for b in self.priority(&OrderType::Buy) {
+            let buy = &self.buy_orders[b];
+            if let Some(s) = self.next_counterparty(&OrderType::Sell, &buy.owner, |price| buy.price >= price) {
+                return Some((b, s));
+            }
         }
+        None
+    }
+
+    // Index of the first order on `side`, in matching priority, that an order from `owner`
+    // may trade with at a price `acceptable` allows. Priority is best price first, so the
+    // search stops at the first unacceptable price.
+    fn next_counterparty<F: Fn(f64) -> bool>(&self, side: &OrderType, owner: &str, acceptable: F) -> Option<usize> {
+        let orders = self.get_orders_by_type(side);
+        for i in self.priority(side) {
+            if !acceptable(orders[i].price) {
+                return None;
+            }
+            if !Self::is_self_trade(owner, &orders[i].owner) {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    // Indexes into one side in matching order: best price first, with ties broken by the
+    // match policy. Each side is already kept in price-time order, which PriceTime uses as is.
+    fn priority(&self, side: &OrderType) -> Vec<usize> {
+        let orders = self.get_orders_by_type(side);
+        let mut indexes: Vec<usize> = (0..orders.len()).collect();
+        if self.match_policy == MatchPolicy::PriceSize {
+            // Stable, so equal amounts at one price stay in time order
+            indexes.sort_by(|&a, &b| {
+                let by_price = match side {
+                    OrderType::Buy => orders[b].price.total_cmp(&orders[a].price),
+                    OrderType::Sell => orders[a].price.total_cmp(&orders[b].price),
+                };
+                by_price.then(orders[b].amount.total_cmp(&orders[a].amount))
+            });
+        }
+        indexes
+    }
+
+    // Anonymous orders never count as a self-trade
+    fn is_self_trade(owner: &str, other_owner: &str) -> bool {
+        !owner.is_empty() && owner == other_owner
+    }
+
+    // Execute an anonymous market order, see submit_market_for
+    fn submit_market(&mut self, side: OrderType, amount: f64) -> Result<Vec<Trade>, OrderError> {
+        self.submit_market_for("", side, amount)
+    }
+
+    // Execute a market order from `owner` against the opposite side, picking resting orders
+    // the way match_orders does: match policy order, skipping the owner's own orders.
+    // It takes the next order id only if something trades, so a market order that finds no
+    // counterparty leaves no trace; any amount left once the book is exhausted is dropped.
+    fn submit_market_for(&mut self, owner: &str, side: OrderType, amount: f64) -> Result<Vec<Trade>, OrderError> {
+        if !amount.is_finite() {
+            return Err(OrderError::NotFinite);
+        }
+        if amount <= 0.0 {
+            return Err(OrderError::NonPositiveAmount);
+        }
+        let mut trades = Vec::new();
+
+        let id = self.next_id;
+        let resting_type = match side {
+            OrderType::Buy => OrderType::Sell,
+            OrderType::Sell => OrderType::Buy,
+        };
+
+        let mut remaining = amount;
+        while remaining > 0.0 {
+            let Some(pos) = self.next_counterparty(&resting_type, owner, |_| true) else {
+                break;
+            };
+            let resting = &self.get_orders_by_type(&resting_type)[pos];
+
+            // Every fill executes at the resting order's own price
+            let fill = remaining.min(resting.amount);
+            let (buy_id, sell_id) = match side {
+                OrderType::Buy => (id, resting.id),
+                OrderType::Sell => (resting.id, id),
+            };
+            let (maker_fee, taker_fee) = self.fee_schedule.fees(fill, resting.price);
+            let trade = Trade { buy_id, sell_id, amount: fill, price: resting.price, maker_fee, taker_fee };
+
+            self.apply_fill(&trade);
+            remaining -= fill;
+            trades.push(trade);
+        }
+        if !trades.is_empty() {
+            self.next_id += 1;
+        }
+        self.record_trades(&trades);
+
+        Ok(trades)
+    }
+
+    // Fill-or-kill: execute the whole amount immediately at `price` or better,
+    // or leave the book untouched if the opposite side can't cover it.
+    fn submit_fok(&mut self, side: OrderType, amount: f64, price: f64) -> Result<Vec<Trade>, OrderError> {
+        self.validate_order(amount, price)?;
+
+        let available: f64 = match side {
+            OrderType::Buy => self.sell_orders.iter().filter(|o| o.price <= price).map(|o| o.amount).sum(),
+            OrderType::Sell => self.buy_orders.iter().filter(|o| o.price >= price).map(|o| o.amount).sum(),
+        };
+        if available < amount {
+            return Err(OrderError::InsufficientLiquidity);
+        }
+
+        // Acceptable levels sit at the front of the opposite side, so a market
+        // order for `amount` never reaches a worse price
+        self.submit_market(side, amount)
+    }
+}
+
+// Interactive mode
+impl OrderBook {
+    fn display_menu(&self) {
+        println!("\n --------- ORDER BOOK ---------");
+        println!("1. Add Buy Order");
+        println!("2. Add Sell Order");
+        println!("3. View Order Book");
+        println!("4. Cancel Order");
+        println!("5. Exit");
+        print!("Enter your choice (1-5): ");
+        io::stdout().flush().unwrap();
+    }
+
+    fn add_order_menu(&mut self, side: OrderType) {
+        print!("Enter amount: ");
+        io::stdout().flush().unwrap();
+        let amount = read_input_line(&mut io::stdin().lock()).unwrap_or_default();
+        print!("Enter price: ");
+        io::stdout().flush().unwrap();
+        let price = read_input_line(&mut io::stdin().lock()).unwrap_or_default();
+
+        let result = parse_order_input(&amount, &price)
+            .and_then(|(amount, price)| self.add_order(side.clone(), amount, price).map_err(|e| e.to_string()));
+        match result {
+            Ok(id) => println!(" {:?} order placed with ID {}", side, id),
+            Err(e) => println!(" Order rejected: {}", e),
+        }
+    }
+
+    fn cancel_order_menu(&mut self) {
+        print!("Enter order ID: ");
+        io::stdout().flush().unwrap();
+        let input = read_input_line(&mut io::stdin().lock()).unwrap_or_default();
+        match parse_order_id(&input) {
+            Ok(_) => match self.cancel_order(&input) {
+                Ok(order) => println!(" Cancelled {}", order),
+                Err(e) => println!(" {}", e),
+            },
+            Err(e) => println!(" Invalid order ID: {}", e),
+        }
+    }
+
+    fn run(&mut self) {
+        println!(" Welcome to the Order Book!");
+
+        loop {
+            self.display_menu();
+            let Some(input) = read_input_line(&mut io::stdin().lock()) else {
+                println!("\n\n Input closed - exiting Order Book. Goodbye!");
+                break;
+            };
+
+            match MenuChoice::from_input(&input) {
+                MenuChoice::AddBuy => self.add_order_menu(OrderType::Buy),
+                MenuChoice::AddSell => self.add_order_menu(OrderType::Sell),
+                MenuChoice::ViewBook => self.show_order_book(),
+                MenuChoice::CancelOrder => self.cancel_order_menu(),
+                MenuChoice::Exit => {
+                    println!("\n Thank you for using the Order Book!");
+                    break;
+                }
+                MenuChoice::Invalid(invalid_input) => {
+                    println!(" Invalid choice: '{}'. Please enter 1-5.", invalid_input);
+                }
+            }
+        }
+    }
+}
+
+// Amount and price as typed at the prompts, both required to be positive numbers
+fn parse_order_input(amount: &str, price: &str) -> Result<(f64, f64), String> {
+    let amount = parse_positive_f64(amount).map_err(|e| format!("amount {}", e))?;
+    let price = parse_positive_f64(price).map_err(|e| format!("price {}", e))?;
+    Ok((amount, price))
+}
+
+// Maker plus taker fees across a set of trades
+fn total_fees(trades: &[Trade]) -> f64 {
+    trades.iter().map(|trade| trade.maker_fee + trade.taker_fee).sum()
+}
+
+// Tick size a new book starts with - one cent
+fn default_tick_size() -> f64 {
+    0.01
+}
+
+// Whether `value` is a whole number of `step`s, allowing for floating-point error
+fn is_multiple_of(value: f64, step: f64) -> bool {
+    let steps = value / step;
+    (steps - steps.round()).abs() < 1e-6
+}
+
+// "B-1" for buy order 1, "S-2" for sell order 2
+fn format_order_id(side: &OrderType, seq: u32) -> String {
+    let prefix = match side {
+        OrderType::Buy => "B",
+        OrderType::Sell => "S",
+    };
+    format!("{}-{}", prefix, seq)
+}
+
+// Split an id like "B-1" (either case) into its side and sequence number
+fn parse_order_id(input: &str) -> Result<(OrderType, u32), String> {
+    let trimmed = input.trim();
+    let side = match trimmed.get(..2).map(|prefix| prefix.to_ascii_uppercase()).as_deref() {
+        Some("B-") => OrderType::Buy,
+        Some("S-") => OrderType::Sell,
+        _ => return Err(format!("'{}' should look like B-1 or S-1", trimmed)),
+    };
+    let seq = parse_positive_u64(&trimmed[2..])?;
+    let seq = u32::try_from(seq).map_err(|_| format!("'{}' is too large", trimmed))?;
+    Ok((side, seq))
+}
+
+// Read one trimmed line, None once the input is closed
+fn read_input_line<R: io::BufRead>(reader: &mut R) -> Option<String> {
+    let mut input = String::new();
+    match reader.read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input.trim().to_string()),
+    }
+}
+
+// Orders as an aligned table with a header row, one line per order
+fn render_table(orders: &[Order]) -> String {
+    let mut table = format!("  {:>4} | {:<4} | {:>10} | {:>10} | {}\n", "ID", "Type", "Amount", "Price", "Owner");
+    for order in orders {
+        let row = format!("  {:>4} | {:<4} | {:>10.2} | {:>10} | {}",
+                          order.order_id(), format!("{:?}", order.order_type), order.amount,
+                          format!("${:.2}", order.price), order.owner);
+        table.push_str(row.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+// Current time in nanoseconds since the Unix epoch
+fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos()
+}
+
+fn main() {
+    // `--interactive` opens the menu instead of running the scripted demo
+    if std::env::args().any(|arg| arg == "--interactive") {
+        OrderBook::new().run();
+        return;
+    }
+
+    println!(" Order Book System Demo\n");
+
+    let mut order_book = OrderBook::new();
+
+    println!("Adding buy orders...");
+    for (amount, price) in [(100.0, 50.25), (200.0, 49.80), (150.0, 51.00)] {
+        if let Err(e) = order_book.add_order(OrderType::Buy, amount, price) {
+            println!("  Rejected buy order: {}", e);
+        }
+    }
+
+    println!("Adding sell orders...");
+    for (amount, price) in [(75.0, 52.50), (300.0, 53.20), (125.0, 51.75)] {
+        if let Err(e) = order_book.add_order(OrderType::Sell, amount, price) {
+            println!("  Rejected sell order: {}", e);
+        }
+    }
+
+    println!("Adding an invalid order...");
+    if let Err(e) = order_book.add_order(OrderType::Buy, 0.0, 50.0) {
+        println!("  Rejected buy order: {}", e);
+    }
+
+    order_book.show_order_book();
+    order_book.show_depth();
+
+    println!(" Order Book Statistics:");
+    let stats = order_book.book_stats();
+    println!("Total orders: {}", order_book.total_orders());
+    println!("Buy orders: {} | Volume: {:.2} | Value: ${:.2}", stats.buy_count, stats.buy_volume, stats.buy_value);
+    println!("Sell orders: {} | Volume: {:.2} | Value: ${:.2}", stats.sell_count, stats.sell_volume, stats.sell_value);
+
+    println!("\n Finding order by ID:");
+    if let Some(order) = order_book.find_order_by_id("B-3") {
+        println!("Found {}", order);
+    }
+
+    println!("\n Orders priced above $51.00:");
+    for order in order_book.filter_orders(|order| order.price > 51.0) {
+        println!("  ID: {} | {:?} @ ${:.2}", order.order_id(), order.order_type, order.price);
+    }
+
+    // Demonstrate total value calculations
+    let buy_total = order_book.get_total_value_by_type(&OrderType::Buy);
+    let sell_total = order_book.get_total_value_by_type(&OrderType::Sell);
+    println!("\n Total Values:");
+    println!("Buy orders total value: ${:.2}", buy_total);
+    println!("Sell orders total value: ${:.2}", sell_total);
+    if let Some(imbalance) = order_book.notional_imbalance() {
+        println!("Bid/ask imbalance: {:+.3}", imbalance);
+    }
+    let sizes = summarize(order_book.buy_orders.iter().chain(&order_book.sell_orders).map(|o| o.amount));
+    println!("Order size - min: {:.2} | max: {:.2} | mean: {:.2}", sizes.min, sizes.max, sizes.mean);
+
+    println!("\n Sell orders from highest to lowest price:");
+    print!("{}", render_table(&order_book.sorted_orders(&OrderType::Sell, true)));
+
+    // Demonstrate immutable borrowing
+    let buy_orders_ref = order_book.get_orders_by_type(&OrderType::Buy);
+    println!("\n Buy orders via reference: {} orders", buy_orders_ref.len());
+
+    // Demonstrate pattern matching with order types
+    let order_types = vec![OrderType::Buy, OrderType::Sell];
+    for ot in &order_types {
+        let count = order_book.get_orders_by_type(ot).len();
+        match ot {
+            OrderType::Buy => println!(" Buy orders count: {}", count),
+            OrderType::Sell => println!(" Sell orders count: {}", count),
+        }
+    }
+
+    println!("\n Top of book:");
+    if let (Some(bid), Some(ask)) = (order_book.best_bid(), order_book.best_ask()) {
+        println!("Best bid: ${:.2} (ID {}) | Best ask: ${:.2} (ID {})", bid.price, bid.order_id(), ask.price, ask.order_id());
+    }
+    if let Some(spread) = order_book.spread() {
+        println!("Spread: ${:.2}", spread);
+    }
+    if let Some(mid) = order_book.mid_price() {
+        println!("Mid price: ${:.2}", mid);
+    }
+    if let (Some(buy_vwap), Some(sell_vwap)) = (order_book.vwap(&OrderType::Buy), order_book.vwap(&OrderType::Sell)) {
+        println!("Buy VWAP: ${:.2} | Sell VWAP: ${:.2}", buy_vwap, sell_vwap);
+    }
+
+    let mut auction = OrderBook::new();
+    if let Err(e) = auction.add_orders(&[
+        (OrderType::Buy, 10.0, 52.0), (OrderType::Buy, 10.0, 51.0), (OrderType::Buy, 10.0, 50.0),
+        (OrderType::Sell, 10.0, 49.0), (OrderType::Sell, 10.0, 50.0), (OrderType::Sell, 10.0, 53.0),
+    ]) {
+        println!("  Rejected auction orders: {}", e);
+    }
+    match auction.uncross() {
+        Some((price, volume)) => println!("\n Auction uncross: {:.2} units clear at ${:.2}", volume, price),
+        None => println!("\n Auction uncross: book does not cross"),
+    }
+
+    println!("\n Cancelling order ID B-2...");
+    match order_book.cancel_order("B-2") {
+        Ok(order) => println!("Cancelled: {:?} - Amount: {}, Price: ${}",
+                              order.order_type, order.amount, order.price),
+        Err(e) => println!("Could not cancel: {}", e),
+    }
+
+    println!("\n Pulling all sell orders at $53.20...");
+    let pulled = order_book.cancel_at_price(&OrderType::Sell, 53.20, 1e-9);
+    println!("Cancelled {} order(s)", pulled);
+
+    println!("\n Modifying order ID B-1 to 120.00 @ $50.50...");
+    match order_book.modify_order("B-1", Some(120.0), Some(50.50)) {
+        Ok(id) => println!("Order ID B-1 updated, now {}", id),
+        Err(e) => println!("Could not modify order: {}", e),
+    }
+
+    println!("\n Resting value (excluding crossing volume):");
+    println!("Buy: ${:.2} | Sell: ${:.2}",
+             order_book.resting_value(&OrderType::Buy), order_book.resting_value(&OrderType::Sell));
+
+    println!("\n Size priority:");
+    let mut venue = OrderBook::new();
+    venue.set_match_policy(MatchPolicy::PriceSize);
+    if let Err(e) = venue.add_orders(&[
+        (OrderType::Sell, 3.0, 50.0), (OrderType::Sell, 8.0, 50.0), (OrderType::Buy, 5.0, 50.0),
+    ]) {
+        println!("  Rejected orders: {}", e);
+    }
+    for trade in venue.match_orders() {
+        println!("  Trade: buy B-{} / sell S-{} | Amount: {:.2} (the larger S-2 fills first)", trade.buy_id, trade.sell_id, trade.amount);
+    }
+
+    println!("\n Self-trade prevention:");
+    let mut desk = OrderBook::new();
+    for (owner, order_type, price) in [
+        ("alice", OrderType::Sell, 50.0), ("bob", OrderType::Sell, 50.5), ("alice", OrderType::Buy, 51.0),
+    ] {
+        if let Err(e) = desk.add_order_for(owner, order_type, 10.0, price) {
+            println!("  Rejected order: {}", e);
+        }
+    }
+    for trade in desk.match_orders() {
+        println!("  Trade: buy B-{} / sell S-{} | Amount: {:.2} | Price: ${:.2} (alice's own sell S-1 skipped)",
+                 trade.buy_id, trade.sell_id, trade.amount, trade.price);
+    }
+
+    let report = order_book.snapshot();
+    let reporter = std::thread::spawn(move || report.book_stats());
+
+    println!("\n Liquidating 300.00 units into the bids would raise ${:.2}",
+             order_book.liquidation_value(300.0));
+
+    let placed_at = now_nanos();
+
+    // Add a crossing order and run the matching engine
+    println!("\n Enforcing a $0.05 tick size and 0.5-unit lots...");
+    let mut ticked = OrderBook::new();
+    if let Err(e) = ticked.set_tick_size(0.05).and_then(|_| ticked.set_min_lot(0.5)) {
+        println!("  Could not configure the book: {}", e);
+    }
+    for (amount, price) in [(1.0, 50.25), (1.0, 50.23), (0.2, 50.25), (1.5, 50.25)] {
+        match ticked.add_order(OrderType::Buy, amount, price) {
+            Ok(id) => println!("  {:.2} @ ${:.2} accepted as {}", amount, price, id),
+            Err(e) => println!("  {:.2} @ ${:.2} rejected: {}", amount, price, e),
+        }
+    }
+
+    println!("\n Matching orders with 2 bps maker / 5 bps taker fees...");
+    order_book.set_fee_schedule(FeeSchedule { maker_bps: 2, taker_bps: 5 });
+    if let Err(e) = order_book.add_order(OrderType::Buy, 100.0, 52.00) {
+        println!("  Rejected buy order: {}", e);
+    }
+    let trades = order_book.match_orders();
+    for trade in &trades {
+        println!("  Trade: buy B-{} / sell S-{} | Amount: {:.2} | Price: ${:.2} | Fees: ${:.2} maker, ${:.2} taker",
+                 trade.buy_id, trade.sell_id, trade.amount, trade.price, trade.maker_fee, trade.taker_fee);
+    }
+    println!("  Total fees: ${:.2}", total_fees(&trades));
+    order_book.show_order_book();
+
+    println!(" Orders placed since the matching step began: {}", order_book.orders_since(placed_at).len());
+
+    println!(" Submitting a market sell for 120.00...");
+    match order_book.submit_market(OrderType::Sell, 120.0) {
+        Ok(fills) => {
+            for trade in &fills {
+                println!("  Fill: buy B-{} | Amount: {:.2} | Price: ${:.2}", trade.buy_id, trade.amount, trade.price);
+            }
+            let filled: f64 = fills.iter().map(|t| t.amount).sum();
+            println!("  Filled {:.2} of 120.00", filled);
+        }
+        Err(e) => println!("  Rejected: {}", e),
+    }
+
+    println!(" Submitting a fill-or-kill buy for 500.00 @ $52.00...");
+    match order_book.submit_fok(OrderType::Buy, 500.0, 52.0) {
+        Ok(trades) => println!("  Filled in {} trade(s)", trades.len()),
+        Err(e) => println!("  Rejected: {}", e),
+    }
+
+    println!("\n Adding the same sell order twice...");
+    for _ in 0..2 {
+        if let Err(e) = order_book.add_order(OrderType::Sell, 10.0, 60.0) {
+            println!("  Rejected sell order: {}", e);
+        }
+    }
+    let before = order_book.total_orders();
+    order_book.dedup_orders();
+    println!(" Removed {} duplicate order(s)", before - order_book.total_orders());
+
+    let expired = order_book.expire_older_than(60 * 1_000_000_000, now_nanos());
+    println!(" Expired {} order(s) older than 60 seconds", expired.len());
+
+    println!("\n Placing a funded buy from a $10,000.00 wallet...");
+    let mut wallet = funding::Wallet::new("carol", 10_000.0);
+    match funding::place_funded_buy(&mut order_book, &mut wallet, 50.0, 49.0) {
+        Ok(id) => {
+            println!("  Order ID {} placed, wallet balance ${:.2}", id, wallet.balance);
+            match funding::cancel_funded_buy(&mut order_book, &mut wallet, &id) {
+                Ok(refund) => println!("  Cancelled and refunded ${:.2}, wallet balance ${:.2}", refund, wallet.balance),
+                Err(e) => println!("  Could not cancel: {}", e),
+            }
+        }
+        Err(e) => println!("  Rejected: {}", e),
+    }
+
+    if let Ok(stats) = reporter.join() {
+        println!("\n Snapshot taken before matching: {} buy / {} sell orders",
+                 stats.buy_count, stats.sell_count);
+    }
+
+    let path = std::env::temp_dir().join("trade_book.json");
+    let path = path.to_string_lossy();
+    match order_book.save(&path).and_then(|_| OrderBook::load(&path)) {
+        Ok(restored) => println!("\n Saved and restored {} orders (next id {}) from {}",
+                                 restored.total_orders(), restored.next_id, path),
+        Err(e) => println!("\n Could not persist order book: {}", e),
+    }
+
+    println!("\n Stop orders:");
+    let mut stops = OrderBook::new();
+    let placed = stops.add_orders(&[(OrderType::Buy, 5.0, 50.0), (OrderType::Buy, 5.0, 48.0)])
+        .and_then(|_| stops.add_stop(OrderType::Sell, 49.0, 47.5, 5.0));
+    match placed {
+        Ok(stop_id) => println!("  Sell stop {} waits for a trade at or below $49.00", stop_id),
+        Err(e) => println!("  Rejected: {}", e),
+    }
+    for _ in 0..2 {
+        if let Err(e) = stops.submit_market(OrderType::Sell, 5.0) {
+            println!("  Rejected market sell: {}", e);
+        }
+        if let Some(last) = stops.last_trade_price {
+            println!("  Last trade ${:.2} -> activated {:?}", last, stops.check_stops());
+        }
+    }
+
+    println!("\n Session ledger: {} trade(s), taker P&L ${:.2}",
+             order_book.trade_log().len(), order_book.session_pnl());
+
+    let replayed = OrderBook::replay(&order_book.events);
+    println!(" Replayed {} logged event(s) into a book with {} orders (live book: {})",
+             order_book.events.len(), replayed.total_orders(), order_book.total_orders());
+
+    if let Err(e) = order_book.clear(false) {
+        println!(" Could not clear the book: {}", e);
+    }
+    println!(" Session closed - book cleared, next order id stays {}", order_book.next_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_valid_order_returns_id() {
+        let mut book = OrderBook::new();
+        assert_eq!(book.add_order(OrderType::Buy, 10.0, 50.0), Ok("B-1".to_string()));
+        assert_eq!(book.add_order(OrderType::Sell, 5.0, 51.0), Ok("S-2".to_string()));
+        assert_eq!(book.total_orders(), 2);
+    }
+
+    #[test]
+    fn test_add_order_rejects_zero_amount() {
+        let mut book = OrderBook::new();
+        assert_eq!(book.add_order(OrderType::Buy, 0.0, 50.0), Err(OrderError::NonPositiveAmount));
+        assert_eq!(book.add_order(OrderType::Buy, 10.0, -1.0), Err(OrderError::NonPositivePrice));
+        assert_eq!(book.total_orders(), 0);
+        assert_eq!(book.next_id, 1);
+    }
+
+    #[test]
+    fn test_add_order_rejects_nan_price() {
+        let mut book = OrderBook::new();
+        assert_eq!(book.add_order(OrderType::Sell, 10.0, f64::NAN), Err(OrderError::NotFinite));
+        assert_eq!(book.add_order(OrderType::Sell, f64::INFINITY, 10.0), Err(OrderError::NotFinite));
+        assert_eq!(book.total_orders(), 0);
+    }
+
+    #[test]
+    fn test_tick_size_enforced() {
+        let mut book = OrderBook::new();
+        book.set_tick_size(0.05).unwrap();
+        assert!(book.add_order(OrderType::Buy, 1.0, 50.25).is_ok());
+        assert_eq!(book.add_order(OrderType::Buy, 1.0, 50.23), Err(OrderError::InvalidTick));
+        assert_eq!(book.modify_order("B-1", None, Some(50.26)), Err(OrderError::InvalidTick));
+        assert_eq!(book.total_orders(), 1);
+
+    }
+
+    #[test]
+    fn test_bad_tick_and_lot_sizes_rejected() {
+        let mut book = OrderBook::new();
+        book.set_tick_size(0.05).unwrap();
+        book.set_min_lot(0.5).unwrap();
+        for bad in [0.0, -0.05, f64::NAN, f64::INFINITY] {
+            let expected = if bad.is_finite() { OrderError::NonPositiveSize } else { OrderError::NotFinite };
+            assert_eq!(book.set_tick_size(bad), Err(expected.clone()));
+            assert_eq!(book.set_min_lot(bad), Err(expected));
+        }
+        assert_eq!((book.tick_size, book.min_lot), (0.05, 0.5));
+        assert_eq!(book.add_order(OrderType::Buy, 1.0, 50.23), Err(OrderError::InvalidTick));
+        assert_eq!(book.add_order(OrderType::Buy, 0.2, 50.25), Err(OrderError::InvalidLot));
+    }
+
+    #[test]
+    fn test_min_lot_enforced() {
+        let mut book = OrderBook::new();
+        book.set_min_lot(0.5).unwrap();
+        assert_eq!(book.add_order(OrderType::Sell, 0.2, 50.0), Err(OrderError::InvalidLot));
+        assert_eq!(book.add_order(OrderType::Sell, 0.75, 50.0), Err(OrderError::InvalidLot));
+        assert!(book.add_order(OrderType::Sell, 1.5, 50.0).is_ok());
+        assert!(book.add_order(OrderType::Sell, 0.5, 50.0).is_ok());
+        assert_eq!(book.total_orders(), 2);
+    }
+
+    #[test]
+    fn test_order_error_messages() {
+        let cases = [
+            (OrderError::NonPositiveAmount, "amount must be greater than zero"),
+            (OrderError::NonPositivePrice, "price must be greater than zero"),
+            (OrderError::NotFinite, "amount and price must be finite numbers"),
+            (OrderError::InvalidTick, "price must be a multiple of the tick size"),
+            (OrderError::InvalidLot, "amount must be a whole number of lots"),
+            (OrderError::OrderNotFound("B-7".to_string()), "no order with id B-7"),
+            (OrderError::InsufficientLiquidity, "could not fully fill"),
+            (OrderError::FundedOrder, "funded orders can't be amended - cancel and place again"),
+            (OrderError::IdsInUse, "ids can't be reset once funded orders have been placed"),
+            (OrderError::NonPositiveSize, "tick and lot sizes must be greater than zero"),
+        ];
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
+        }
+        let boxed: Box<dyn std::error::Error> = Box::new(OrderError::InvalidTick);
+        assert_eq!(boxed.to_string(), "price must be a multiple of the tick size");
+    }
+
+    #[test]
+    fn test_market_order_rejects_bad_amount() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 5.0, 50.0).unwrap();
+        assert_eq!(book.submit_market(OrderType::Buy, 0.0), Err(OrderError::NonPositiveAmount));
+        assert_eq!(book.submit_market(OrderType::Buy, f64::NAN), Err(OrderError::NotFinite));
+        assert_eq!(book.next_id, 2);
+    }
+
+    #[test]
+    fn test_add_orders_valid_batch() {
+        let mut book = OrderBook::new();
+        let ids = book.add_orders(&[
+            (OrderType::Buy, 10.0, 50.0),
+            (OrderType::Sell, 5.0, 51.0),
+            (OrderType::Buy, 2.0, 49.0),
+        ]);
+        assert_eq!(ids, Ok(vec!["B-1".to_string(), "S-2".to_string(), "B-3".to_string()]));
+        assert_eq!(book.buy_orders.len(), 2);
+        assert_eq!(book.sell_orders.len(), 1);
+    }
+
+    #[test]
+    fn test_add_orders_bad_entry_inserts_none() {
+        let mut book = OrderBook::new();
+        let result = book.add_orders(&[
+            (OrderType::Buy, 10.0, 50.0),
+            (OrderType::Sell, 5.0, f64::NAN),
+            (OrderType::Buy, 2.0, 49.0),
+        ]);
+        assert_eq!(result, Err(OrderError::NotFinite));
+        assert_eq!(book.total_orders(), 0);
+        assert_eq!(book.next_id, 1);
+    }
+
+    #[test]
+    fn test_cancel_at_price_removes_matching_orders() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 1.0, 50.1).unwrap();
+        book.add_order(OrderType::Sell, 2.0, 50.2).unwrap();
+        book.add_order(OrderType::Sell, 3.0, 0.1 + 0.2 + 49.9).unwrap();
+        book.add_order(OrderType::Buy, 3.0, 50.2).unwrap();
+
+        assert_eq!(book.cancel_at_price(&OrderType::Sell, 50.2, 1e-9), 2);
+        assert_eq!(book.sell_orders.len(), 1);
+        assert_eq!(book.sell_orders[0].id, 1);
+        assert_eq!(book.buy_orders.len(), 1);
+        assert_eq!(book.cancel_at_price(&OrderType::Sell, 99.0, 1e-9), 0);
+    }
+
+    #[test]
+    fn test_modify_amount_keeps_priority() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+
+        assert!(book.modify_order("B-1", Some(25.0), None).is_ok());
+        assert_eq!(book.buy_orders[0].id, 1);
+        assert_eq!(book.buy_orders[0].amount, 25.0);
+        assert_eq!(book.modify_order("B-1", Some(0.0), None), Err(OrderError::NonPositiveAmount));
+    }
+
+    #[test]
+    fn test_modify_price_repositions_order() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 49.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 48.0).unwrap();
+
+        assert_eq!(book.modify_order("B-1", None, Some(49.0)), Ok("B-4".to_string()));
+        let ids: Vec<u32> = book.buy_orders.iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![2, 4, 3]);
+        assert_eq!(book.buy_orders[1].price, 49.0);
+        assert!(book.find_order_by_id("B-1").is_none());
+    }
+
+    #[test]
+    fn test_repriced_order_that_crosses_is_the_taker() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 1.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 1.0, 50.0).unwrap();
+
+        let id = book.modify_order("B-1", None, Some(51.0)).unwrap();
+        assert_eq!(id, "B-3");
+        let trades = book.match_orders();
+        assert_eq!((trades[0].buy_id, trades[0].sell_id, trades[0].price), (3, 2, 50.0));
+        assert_eq!(trades[0].taker_side(), OrderType::Buy);
+    }
+
+    #[test]
+    fn test_modify_unknown_order() {
+        let mut book = OrderBook::new();
+        assert_eq!(book.modify_order("B-7", Some(1.0), None), Err(OrderError::OrderNotFound("B-7".to_string())));
+        // Reported the same way as cancel_order reports it
+        assert_eq!(book.modify_order(" b-7 ", Some(1.0), None), Err(OrderError::OrderNotFound("B-7".to_string())));
+        assert_eq!(book.cancel_order(" b-7 ").unwrap_err(), OrderError::OrderNotFound("B-7".to_string()));
+    }
+
+    #[test]
+    fn test_timestamps_are_monotonic() {
+        let mut book = OrderBook::new();
+        for price in [50.0, 51.0, 49.0] {
+            book.add_order(OrderType::Buy, 1.0, price).unwrap();
+        }
+        let stamps: Vec<u128> = (1..=3).map(|id| book.find_order_by_id(&format!("B-{}", id)).unwrap().timestamp).collect();
+        assert!(stamps[0] <= stamps[1] && stamps[1] <= stamps[2]);
+    }
+
+    #[test]
+    fn test_orders_since_filters_by_time() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 1.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 1.0, 55.0).unwrap();
+        book.buy_orders[0].timestamp = 100;
+        book.sell_orders[0].timestamp = 200;
+
+        assert_eq!(book.orders_since(0).len(), 2);
+        let recent = book.orders_since(150);
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].id, 2);
+        assert_eq!(book.orders_since(200).len(), 1);
+        assert!(book.orders_since(201).is_empty());
+    }
+
+    #[test]
+    fn test_expire_older_than_removes_only_stale_orders() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 51.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 52.0).unwrap();
+        book.buy_orders[0].timestamp = 100;
+        book.buy_orders[1].timestamp = 900;
+        book.sell_orders[0].timestamp = 950;
+        book.sell_orders[1].timestamp = 200;
+
+        let expired = book.expire_older_than(500, 1_000);
+        let mut ids: Vec<u32> = expired.iter().map(|o| o.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 4]);
+        assert_eq!(book.buy_orders.len(), 1);
+        assert_eq!(book.buy_orders[0].id, 2);
+        assert_eq!(book.sell_orders.len(), 1);
+        assert_eq!(book.sell_orders[0].id, 3);
+    }
+
+    #[test]
+    fn test_snapshot_unaffected_by_later_changes() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 51.0).unwrap();
+
+        let snapshot = book.snapshot();
+        book.cancel_order("B-1").unwrap();
+        book.modify_order("S-2", Some(3.0), None).unwrap();
+        book.add_order(OrderType::Buy, 5.0, 49.0).unwrap();
+
+        assert_eq!(snapshot.buy_orders.len(), 1);
+        assert_eq!(snapshot.buy_orders[0].id, 1);
+        assert_eq!(snapshot.sell_orders[0].amount, 10.0);
+        assert_eq!(snapshot.next_id, 3);
+        assert_eq!(book.next_id, 4);
+    }
+
+    #[test]
+    fn test_dedup_orders_keeps_first_duplicate() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 50.0).unwrap();
+
+        book.dedup_orders();
+        let buy_ids: Vec<u32> = book.buy_orders.iter().map(|o| o.id).collect();
+        assert_eq!(buy_ids, vec![1, 3]);
+        assert_eq!(book.sell_orders.len(), 1);
+    }
+
+    #[test]
+    fn test_order_equality_ignores_id_and_timestamp() {
+        let mut book = OrderBook::new();
+        book.add_order_for("alice", OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order_for("bob", OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order_for("alice", OrderType::Buy, 10.0, 50.0).unwrap();
+
+        assert_ne!(book.buy_orders[0], book.buy_orders[1]);
+        assert_eq!(book.buy_orders[0], book.buy_orders[2]);
+        let unique: HashSet<&Order> = book.buy_orders.iter().collect();
+        assert_eq!(unique.len(), 2);
+    }
+
+    #[test]
+    fn test_order_display_is_one_line() {
+        let mut book = OrderBook::new();
+        book.add_order_for("alice", OrderType::Sell, 2.5, 51.0).unwrap();
+        assert_eq!(book.sell_orders[0].to_string(), "ID: S-1 | Type: Sell | Amount: 2.50 | Price: $51.00 | Owner: alice");
+    }
+
+    #[test]
+    fn test_render_table_columns() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 100.0, 50.25).unwrap();
+
+        let table = render_table(&book.buy_orders);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for column in ["ID", "Type", "Amount", "Price", "Owner"] {
+            assert!(lines[0].contains(column));
+        }
+        let cells: Vec<&str> = lines[1].split('|').map(str::trim).collect();
+        assert_eq!(cells, vec!["B-1", "Buy", "100.00", "$50.25", ""]);
+    }
+
+    #[test]
+    fn test_menu_choice_from_input() {
+        assert_eq!(MenuChoice::from_input("1"), MenuChoice::AddBuy);
+        assert_eq!(MenuChoice::from_input("2"), MenuChoice::AddSell);
+        assert_eq!(MenuChoice::from_input(" 3 "), MenuChoice::ViewBook);
+        assert_eq!(MenuChoice::from_input("4"), MenuChoice::CancelOrder);
+        assert_eq!(MenuChoice::from_input("5"), MenuChoice::Exit);
+        assert_eq!(MenuChoice::from_input("buy"), MenuChoice::Invalid("buy".to_string()));
+    }
+
+    #[test]
+    fn test_parse_order_input() {
+        assert_eq!(parse_order_input(" 10 ", "50.25"), Ok((10.0, 50.25)));
+        assert_eq!(parse_order_input("0", "50"), Err("amount '0' must be greater than zero".to_string()));
+        assert_eq!(parse_order_input("10", "abc"), Err("price 'abc' is not a number".to_string()));
+    }
+
+    #[test]
+    fn test_parse_order_id() {
+        assert_eq!(parse_order_id("B-7"), Ok((OrderType::Buy, 7)));
+        assert_eq!(parse_order_id(" s-3 "), Ok((OrderType::Sell, 3)));
+        assert_eq!(parse_order_id("7"), Err("'7' should look like B-1 or S-1".to_string()));
+        assert!(parse_order_id("B--1").is_err());
+        assert!(parse_order_id("B-99999999999").is_err());
+    }
+
+    #[test]
+    fn test_read_input_line_stops_at_eof() {
+        let mut input = io::Cursor::new("3\n");
+        assert_eq!(read_input_line(&mut input), Some("3".to_string()));
+        assert_eq!(read_input_line(&mut input), None);
+    }
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("trade_book_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 52.5).unwrap();
+        book.add_order(OrderType::Sell, 1.0, 53.0).unwrap();
+        book.cancel_order("S-3").unwrap();
+        book.save(path).unwrap();
+
+        let mut restored = OrderBook::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(restored.next_id, 4);
+        assert_eq!(restored.total_orders(), 2);
+        let sell = restored.find_order_by_id("S-2").unwrap();
+        assert_eq!((sell.amount, sell.price), (5.0, 52.5));
+        assert_eq!(restored.add_order(OrderType::Buy, 1.0, 49.0), Ok("B-4".to_string()));
+    }
+
+    #[test]
+    fn test_load_missing_file_is_empty() {
+        let book = OrderBook::load("/nonexistent/dir/trade_book.json").unwrap();
+        assert_eq!(book.total_orders(), 0);
+        assert_eq!(book.next_id, 1);
+    }
+
+    #[test]
+    fn test_filter_orders_by_type() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 1.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 1.0, 52.0).unwrap();
+        book.add_order(OrderType::Sell, 1.0, 53.0).unwrap();
+
+        let sells = book.filter_orders(|order| order.order_type == OrderType::Sell);
+        let ids: Vec<u32> = sells.iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_filter_orders_by_price_threshold() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 1.0, 49.0).unwrap();
+        book.add_order(OrderType::Buy, 1.0, 51.0).unwrap();
+        book.add_order(OrderType::Sell, 1.0, 52.0).unwrap();
+
+        let buys_over_50 = book.filter_orders(|o| o.order_type == OrderType::Buy && o.price > 50.0);
+        assert_eq!(buys_over_50.len(), 1);
+        assert_eq!(buys_over_50[0].id, 2);
+        assert_eq!(book.filter_orders(|o| o.price > 50.0).len(), 2);
+        assert!(book.filter_orders(|o| o.price > 100.0).is_empty());
+    }
+
+    #[test]
+    fn test_clear_keeps_id_counter() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 51.0).unwrap();
+
+        book.clear(false).unwrap();
+        assert_eq!(book.total_orders(), 0);
+        assert_eq!(book.vwap(&OrderType::Buy), None);
+        assert_eq!(book.add_order(OrderType::Buy, 1.0, 50.0), Ok("B-3".to_string()));
+    }
+
+    #[test]
+    fn test_clear_resets_id_counter() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 51.0).unwrap();
+
+        book.clear(true).unwrap();
+        assert_eq!(book.total_orders(), 0);
+        assert_eq!(book.next_id, 1);
+        assert_eq!(book.add_order(OrderType::Buy, 1.0, 50.0), Ok("B-1".to_string()));
+    }
+
+    #[test]
+    fn test_cancel_existing_buy_order() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 5.0, 49.0).unwrap();
+
+        let cancelled = book.cancel_order("B-1").unwrap();
+        assert_eq!(cancelled.id, 1);
+        assert_eq!(cancelled.order_type, OrderType::Buy);
+        assert_eq!(book.buy_orders.len(), 1);
+        assert!(book.find_order_by_id("B-1").is_none());
+    }
+
+    #[test]
+    fn test_buy_order_id_is_prefixed_and_found() {
+        let mut book = OrderBook::new();
+        let id = book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        assert!(id.starts_with("B-"));
+
+        let order = book.find_order_by_id(&id).unwrap();
+        assert_eq!((order.order_type.clone(), order.amount), (OrderType::Buy, 10.0));
+        assert_eq!(order.order_id(), id);
+        assert!(book.find_order_by_id("b-1").is_some());
+        // Same number, wrong side
+        assert!(book.find_order_by_id("S-1").is_none());
+        assert!(book.cancel_order("S-1").is_err());
+    }
+
+    #[test]
+    fn test_cancel_removes_only_one_duplicate() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 10.0, 50.0).unwrap();
+        let duplicate = book.sell_orders[0].clone();
+        book.sell_orders.push(duplicate);
+
+        assert!(book.cancel_order("S-1").is_ok());
+        assert_eq!(book.sell_orders.len(), 1);
+    }
+
+    #[test]
+    fn test_cancel_nonexistent_order() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        assert_eq!(book.cancel_order("B-42").unwrap_err(), OrderError::OrderNotFound("B-42".to_string()));
+        assert_eq!(book.total_orders(), 1);
+    }
+
+    #[test]
+    fn test_buys_sorted_by_price_time_priority() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 49.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 51.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 51.0).unwrap();
+
+        let ids: Vec<u32> = book.buy_orders.iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn test_sells_sorted_ascending() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 10.0, 53.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 51.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 52.0).unwrap();
+
+        let prices: Vec<f64> = book.sell_orders.iter().map(|o| o.price).collect();
+        assert_eq!(prices, vec![51.0, 52.0, 53.0]);
+    }
+
+    #[test]
+    fn test_sorted_orders_buy_side_descending() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 1.0, 49.0).unwrap();
+        book.add_order(OrderType::Buy, 1.0, 51.0).unwrap();
+        book.add_order(OrderType::Buy, 1.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 1.0, 51.0).unwrap();
+
+        let prices: Vec<f64> = book.sorted_orders(&OrderType::Buy, true).iter().map(|o| o.price).collect();
+        assert_eq!(prices, vec![51.0, 51.0, 50.0, 49.0]);
+        let ascending: Vec<u32> = book.sorted_orders(&OrderType::Buy, false).iter().map(|o| o.id).collect();
+        assert_eq!(ascending, vec![1, 3, 2, 4]);
+        assert_eq!(book.buy_orders[0].id, 2);
+    }
+
+    #[test]
+    fn test_depth_aggregates_price_levels() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 5.0, 51.0).unwrap();
+        book.add_order(OrderType::Buy, 2.5, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 1.0, 53.0).unwrap();
+        book.add_order(OrderType::Sell, 2.0, 52.0).unwrap();
+
+        assert_eq!(book.depth(&OrderType::Buy), vec![(51.0, 5.0), (50.0, 12.5)]);
+        assert_eq!(book.depth(&OrderType::Sell), vec![(52.0, 2.0), (53.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_cumulative_depth_fractions() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 2.0, 52.0).unwrap();
+        book.add_order(OrderType::Sell, 1.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 3.0, 51.0).unwrap();
+        book.add_order(OrderType::Sell, 2.0, 52.0).unwrap();
+
+        let depth = book.cumulative_depth(&OrderType::Sell);
+        let prices: Vec<f64> = depth.iter().map(|(price, _, _)| *price).collect();
+        assert_eq!(prices, vec![50.0, 51.0, 52.0]);
+        assert_eq!(depth[2].1, 4.0);
+        assert!(depth.windows(2).all(|pair| pair[0].2 < pair[1].2));
+        assert_eq!(depth[0].2, 0.125);
+        assert_eq!(depth[2].2, 1.0);
+    }
+
+    #[test]
+    fn test_cumulative_depth_empty_side() {
+        let book = OrderBook::new();
+        assert!(book.cumulative_depth(&OrderType::Buy).is_empty());
+    }
+
+    #[test]
+    fn test_best_bid_ask_and_spread() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 49.5).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 50.25).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 48.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 53.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 51.75).unwrap();
+
+        assert_eq!(book.best_bid().unwrap().id, 2);
+        assert_eq!(book.best_ask().unwrap().id, 5);
+        assert_eq!(book.spread(), Some(1.5));
+    }
+
+    #[test]
+    fn test_book_stats() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 20.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 52.0).unwrap();
+
+        assert_eq!(book.book_stats(), BookStats {
+            buy_count: 2,
+            sell_count: 1,
+            buy_volume: 30.0,
+            sell_volume: 5.0,
+            buy_value: 1480.0,
+            sell_value: 260.0,
+        });
+    }
+
+    #[test]
+    fn test_resting_value_non_crossing_book() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 50.0).unwrap();
+
+        assert_eq!(book.resting_value(&OrderType::Buy), book.get_total_value_by_type(&OrderType::Buy));
+        assert_eq!(book.resting_value(&OrderType::Sell), book.get_total_value_by_type(&OrderType::Sell));
+    }
+
+    #[test]
+    fn test_resting_value_crossing_book() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 51.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 48.0).unwrap();
+        book.add_order(OrderType::Sell, 4.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 52.0).unwrap();
+
+        // 4 units of the 51.00 bid cross the 50.00 ask
+        assert_eq!(book.resting_value(&OrderType::Buy), 6.0 * 51.0 + 10.0 * 48.0);
+        assert_eq!(book.resting_value(&OrderType::Sell), 10.0 * 52.0);
+        assert!(book.resting_value(&OrderType::Buy) < book.get_total_value_by_type(&OrderType::Buy));
+        assert_eq!(book.total_orders(), 4);
+    }
+
+    #[test]
+    fn test_liquidation_value_walks_two_levels() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 5.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 48.0).unwrap();
+
+        let proceeds = book.liquidation_value(8.0);
+        assert_eq!(proceeds, 5.0 * 50.0 + 3.0 * 48.0);
+        assert!(proceeds < 8.0 * 50.0);
+        // Only 15 units of bids exist
+        assert_eq!(book.liquidation_value(20.0), 5.0 * 50.0 + 10.0 * 48.0);
+        assert_eq!(OrderBook::new().liquidation_value(1.0), 0.0);
+    }
+
+    #[test]
+    fn test_notional_imbalance_bid_heavy() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 30.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 50.0).unwrap();
+        assert_eq!(book.notional_imbalance(), Some(0.5));
+    }
+
+    #[test]
+    fn test_notional_imbalance_ask_heavy() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 10.0, 60.0).unwrap();
+        assert_eq!(book.notional_imbalance(), Some(-1.0));
+        book.add_order(OrderType::Buy, 10.0, 20.0).unwrap();
+        assert_eq!(book.notional_imbalance(), Some(-0.5));
+    }
+
+    #[test]
+    fn test_notional_imbalance_empty_book() {
+        assert_eq!(OrderBook::new().notional_imbalance(), None);
+    }
+
+    #[test]
+    fn test_vwap_and_mid_price() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 30.0, 48.0).unwrap();
+        book.add_order(OrderType::Sell, 20.0, 52.0).unwrap();
+
+        // (10*50 + 30*48) / 40 = 48.5
+        assert_eq!(book.vwap(&OrderType::Buy), Some(48.5));
+        assert_eq!(book.vwap(&OrderType::Sell), Some(52.0));
+        assert_eq!(book.mid_price(), Some(51.0));
+    }
+
+    #[test]
+    fn test_incremental_vwap_matches_full_recomputation() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 30.0, 48.0).unwrap();
+        book.add_order(OrderType::Buy, 5.0, 49.5).unwrap();
+        book.add_order(OrderType::Sell, 8.0, 52.0).unwrap();
+        book.add_order(OrderType::Sell, 4.0, 53.0).unwrap();
+        book.cancel_order("B-2").unwrap();
+        book.modify_order("B-3", Some(7.0), None).unwrap();
+        book.submit_market(OrderType::Buy, 3.0).unwrap();
+
+        for side in [OrderType::Buy, OrderType::Sell] {
+            let expected = book.get_total_value_by_type(&side) / book.get_total_volume_by_type(&side);
+            assert!((book.vwap(&side).unwrap() - expected).abs() < 1e-9);
+        }
+        assert_eq!(book.vwap(&OrderType::Buy), Some((10.0 * 50.0 + 7.0 * 49.5) / 17.0));
+
+        book.cancel_order("B-1").unwrap();
+        book.cancel_order("B-3").unwrap();
+        assert_eq!(book.vwap(&OrderType::Buy), None);
+        assert_eq!(book.buy_volume, 0.0);
+        assert_eq!(book.buy_notional, 0.0);
+    }
+
+    // (id, amount, price, owner, timestamp) for each order on one side, in book order
+    fn side_fields(orders: &[Order]) -> Vec<(u32, f64, f64, String, u128)> {
+        orders.iter().map(|o| (o.id, o.amount, o.price, o.owner.clone(), o.timestamp)).collect()
+    }
+
+    #[test]
+    fn test_replay_rebuilds_live_book() {
+        let mut book = OrderBook::new();
+        book.set_fee_schedule(FeeSchedule { maker_bps: 5, taker_bps: 20 });
+        book.set_match_policy(MatchPolicy::PriceSize);
+        book.set_tick_size(0.5).unwrap();
+        // Nothing to trade against, so this uses no id
+        book.submit_market(OrderType::Buy, 1.0).unwrap();
+        book.add_order_for("alice", OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 5.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 4.0, 52.0).unwrap();
+        book.add_order(OrderType::Sell, 6.0, 53.0).unwrap();
+        book.add_order(OrderType::Sell, 6.0, 53.0).unwrap();
+        book.cancel_order("B-2").unwrap();
+        book.modify_order("S-3", Some(8.0), None).unwrap();
+        book.modify_order("S-4", None, Some(52.5)).unwrap();
+        book.add_order(OrderType::Buy, 9.0, 52.5).unwrap();
+        book.match_orders();
+        book.submit_market(OrderType::Sell, 3.0).unwrap();
+        book.dedup_orders();
+        // One stop fires on the last trade and one stays pending
+        book.add_stop(OrderType::Sell, 60.0, 55.0, 1.0).unwrap();
+        book.add_stop(OrderType::Buy, 70.0, 71.0, 2.0).unwrap();
+        book.check_stops();
+
+        let replayed = OrderBook::replay(&book.events);
+        let stop_ids = |book: &OrderBook| book.stops.iter().map(|stop| format_order_id(&stop.side, stop.id)).collect::<Vec<_>>();
+        assert_eq!(stop_ids(&replayed), stop_ids(&book));
+        assert_eq!(book.stops.len(), 1);
+        assert_eq!((replayed.fee_schedule, replayed.match_policy, replayed.tick_size), (book.fee_schedule, book.match_policy, 0.5));
+        assert_eq!(replayed.trade_log(), book.trade_log());
+        assert_eq!(side_fields(&replayed.buy_orders), side_fields(&book.buy_orders));
+        assert_eq!(side_fields(&replayed.sell_orders), side_fields(&book.sell_orders));
+        assert_eq!(replayed.next_id, book.next_id);
+        assert_eq!(replayed.events.len(), book.events.len());
+        for side in [OrderType::Buy, OrderType::Sell] {
+            match (replayed.vwap(&side), book.vwap(&side)) {
+                (Some(a), Some(b)) => assert!((a - b).abs() < 1e-9),
+                (a, b) => assert_eq!(a, b),
+            }
+        }
+    }
+
+    #[test]
+    fn test_events_logged_per_mutation() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 1.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 1.0, 50.0).unwrap();
+        book.match_orders();
+        book.add_order(OrderType::Buy, 1.0, 49.0).unwrap();
+        book.cancel_order("b-3").unwrap();
+
+        let kinds: Vec<String> = book.events.iter().map(|event| match event {
+            BookEvent::Added(order) => format!("add {}", order.order_id()),
+            BookEvent::Canceled(id) => format!("cancel {}", id),
+            BookEvent::Matched(trade) => format!("match {}/{}", trade.buy_id, trade.sell_id),
+            BookEvent::Amended(id, amount) => format!("amend {} {}", id, amount),
+            BookEvent::StopAdded(stop) => format!("stop {}", format_order_id(&stop.side, stop.id)),
+            BookEvent::StopFired(id) => format!("fire {}", id),
+            BookEvent::Configured { .. } => "configure".to_string(),
+        }).collect();
+        assert_eq!(kinds, ["add B-1", "add S-2", "match 1/2", "add B-3", "cancel B-3"]);
+    }
+
+    #[test]
+    fn test_incremental_totals_survive_save_load() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 5.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 52.0).unwrap();
+
+        let path = std::env::temp_dir().join(format!("trade_book_totals_{}.json", std::process::id()));
+        let path = path.to_string_lossy();
+        book.save(&path).unwrap();
+        let restored = OrderBook::load(&path).unwrap();
+        fs::remove_file(&*path).unwrap();
+
+        assert_eq!(restored.vwap(&OrderType::Sell), Some(51.0));
+    }
+
+    #[test]
+    fn test_vwap_and_mid_price_empty() {
+        let mut book = OrderBook::new();
+        assert_eq!(book.vwap(&OrderType::Buy), None);
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        assert_eq!(book.mid_price(), None);
+    }
+
+    #[test]
+    fn test_top_of_book_empty_side() {
+        let mut book = OrderBook::new();
+        assert!(book.best_bid().is_none());
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        assert!(book.best_ask().is_none());
+        assert_eq!(book.spread(), None);
+    }
+
+    #[test]
+    fn test_match_full_fill() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 51.0).unwrap();
+
+        let trades = book.match_orders();
+        assert_eq!(trades, vec![Trade { buy_id: 2, sell_id: 1, amount: 10.0, price: 50.0, maker_fee: 0.0, taker_fee: 0.0 }]);
+        assert_eq!(book.total_orders(), 0);
+    }
+
+    #[test]
+    fn test_match_charges_maker_and_taker_fees() {
+        let mut book = OrderBook::new();
+        book.set_fee_schedule(FeeSchedule { maker_bps: 10, taker_bps: 25 });
+        book.add_order(OrderType::Sell, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 51.0).unwrap();
+
+        // Notional 10 * 50 = 500: maker pays 0.10%, taker 0.25%
+        let trades = book.match_orders();
+        assert_eq!(trades.len(), 1);
+        assert!((trades[0].maker_fee - 0.5).abs() < 1e-9);
+        assert!((trades[0].taker_fee - 1.25).abs() < 1e-9);
+        assert!((total_fees(&trades) - 1.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_market_order_pays_taker_fee() {
+        let mut book = OrderBook::new();
+        book.set_fee_schedule(FeeSchedule { maker_bps: 0, taker_bps: 20 });
+        book.add_order(OrderType::Buy, 5.0, 40.0).unwrap();
+
+        let trades = book.submit_market(OrderType::Sell, 5.0).unwrap();
+        assert_eq!(trades[0].maker_fee, 0.0);
+        assert!((trades[0].taker_fee - 0.4).abs() < 1e-9);
+        assert_eq!(total_fees(&[]), 0.0);
+    }
+
+    // Two resting sells at one price - a small early one and a larger later one - and a buy for 5
+    fn same_price_book(policy: MatchPolicy) -> OrderBook {
+        let mut book = OrderBook::new();
+        book.set_match_policy(policy);
+        book.add_order(OrderType::Sell, 3.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 8.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 5.0, 50.0).unwrap();
+        book
+    }
+
+    #[test]
+    fn test_price_time_policy_fills_earliest_first() {
+        let mut book = same_price_book(MatchPolicy::PriceTime);
+        let fills: Vec<(u32, f64)> = book.match_orders().iter().map(|t| (t.sell_id, t.amount)).collect();
+        assert_eq!(fills, vec![(1, 3.0), (2, 2.0)]);
+    }
+
+    #[test]
+    fn test_price_size_policy_fills_largest_first() {
+        let mut book = same_price_book(MatchPolicy::PriceSize);
+        let fills: Vec<(u32, f64)> = book.match_orders().iter().map(|t| (t.sell_id, t.amount)).collect();
+        assert_eq!(fills, vec![(2, 5.0)]);
+        assert_eq!(book.find_order_by_id("S-1").unwrap().amount, 3.0);
+    }
+
+    #[test]
+    fn test_price_size_policy_still_prefers_better_price() {
+        let mut book = OrderBook::new();
+        book.set_match_policy(MatchPolicy::PriceSize);
+        book.add_order(OrderType::Sell, 1.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 9.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 1.0, 50.0).unwrap();
+        assert_eq!(book.match_orders()[0].sell_id, 1);
+    }
+
+    #[test]
+    fn test_buy_then_sell_cycle_pnl() {
+        let mut book = OrderBook::new();
+        // Buy 2 against a resting ask at 50, then sell 2 into a resting bid at 55
+        book.add_order(OrderType::Sell, 2.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 2.0, 50.0).unwrap();
+        book.match_orders();
+        book.add_order(OrderType::Buy, 2.0, 55.0).unwrap();
+        book.add_order(OrderType::Sell, 2.0, 55.0).unwrap();
+        book.match_orders();
+
+        let log = book.trade_log();
+        assert_eq!(log.len(), 2);
+        assert_eq!((log[0].taker_side(), log[1].taker_side()), (OrderType::Buy, OrderType::Sell));
+        assert_eq!(book.session_pnl(), 10.0);
+    }
+
+    #[test]
+    fn test_trade_log_includes_market_fills() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 3.0, 40.0).unwrap();
+        book.submit_market(OrderType::Sell, 3.0).unwrap();
+        assert_eq!(book.trade_log().len(), 1);
+        assert_eq!(book.session_pnl(), 120.0);
+        assert_eq!(OrderBook::replay(&book.events).trade_log(), book.trade_log());
+    }
+
+    #[test]
+    fn test_trade_past_trigger_activates_stop() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 5.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 5.0, 48.0).unwrap();
+        let stop_id = book.add_stop(OrderType::Sell, 49.0, 47.5, 3.0).unwrap();
+        assert_eq!(stop_id, "S-3");
+        assert!(book.check_stops().is_empty());
+
+        // Trading at 50 leaves the stop pending; trading at 48 crosses the trigger
+        book.submit_market(OrderType::Sell, 5.0).unwrap();
+        assert_eq!(book.last_trade_price, Some(50.0));
+        assert!(book.check_stops().is_empty());
+        book.submit_market(OrderType::Sell, 1.0).unwrap();
+        assert_eq!(book.last_trade_price, Some(48.0));
+
+        // The two market orders took ids 4 and 5, so the live order is S-6
+        assert_eq!(book.check_stops(), vec!["S-6".to_string()]);
+        assert!(book.find_order_by_id(&stop_id).is_none());
+        let order = book.find_order_by_id("S-6").unwrap();
+        assert_eq!((order.amount, order.price), (3.0, 47.5));
+        assert!(book.stops.is_empty());
+
+        // The stop's sell at 47.50 crosses the remaining bid at 48
+        let trades = book.match_orders();
+        assert_eq!((trades[0].sell_id, trades[0].amount, trades[0].price), (6, 3.0, 48.0));
+    }
+
+    #[test]
+    fn test_fired_stop_takes_from_orders_placed_after_it() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 1.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 1.0, 50.0).unwrap();
+        book.match_orders();
+        book.add_stop(OrderType::Buy, 50.0, 52.0, 1.0).unwrap();
+        // Rests below the stop's limit, but arrives while the stop is still pending
+        book.add_order(OrderType::Sell, 1.0, 51.0).unwrap();
+
+        assert_eq!(book.check_stops(), vec!["B-5".to_string()]);
+        let trades = book.match_orders();
+        assert_eq!((trades[0].buy_id, trades[0].sell_id, trades[0].price), (5, 4, 51.0));
+        assert_eq!(trades[0].taker_side(), OrderType::Buy);
+    }
+
+    #[test]
+    fn test_buy_stop_waits_for_price_to_rise() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 1.0, 52.0).unwrap();
+        book.add_stop(OrderType::Buy, 55.0, 56.0, 1.0).unwrap();
+        book.submit_market(OrderType::Buy, 1.0).unwrap();
+        assert!(book.check_stops().is_empty());
+        assert_eq!(book.stops.len(), 1);
+        assert_eq!(book.add_stop(OrderType::Buy, 55.0, 56.0, 0.0), Err(OrderError::NonPositiveAmount));
+    }
+
+    #[test]
+    fn test_match_partial_fill() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 51.0).unwrap();
+        book.add_order(OrderType::Sell, 4.0, 50.0).unwrap();
+
+        let trades = book.match_orders();
+        assert_eq!(trades, vec![Trade { buy_id: 1, sell_id: 2, amount: 4.0, price: 51.0, maker_fee: 0.0, taker_fee: 0.0 }]);
+        assert!(book.sell_orders.is_empty());
+        assert_eq!(book.buy_orders.len(), 1);
+        assert_eq!(book.buy_orders[0].amount, 6.0);
+    }
+
+    #[test]
+    fn test_match_price_time_priority() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 5.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 49.0).unwrap();
+        book.add_order(OrderType::Buy, 12.0, 50.0).unwrap();
+
+        let trades = book.match_orders();
+        let sells: Vec<u32> = trades.iter().map(|t| t.sell_id).collect();
+        assert_eq!(sells, vec![2, 3, 1]);
+        assert_eq!(trades[2].amount, 2.0);
+        assert_eq!(book.sell_orders[0].amount, 3.0);
+    }
+
+    #[test]
+    fn test_market_buy_crosses_two_levels() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 5.0, 51.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 52.0).unwrap();
+
+        let trades = book.submit_market(OrderType::Buy, 8.0).unwrap();
+        assert_eq!(trades, vec![
+            Trade { buy_id: 4, sell_id: 2, amount: 5.0, price: 50.0, maker_fee: 0.0, taker_fee: 0.0 },
+            Trade { buy_id: 4, sell_id: 1, amount: 3.0, price: 51.0, maker_fee: 0.0, taker_fee: 0.0 },
+        ]);
+        assert_eq!(book.sell_orders.len(), 2);
+        assert_eq!(book.sell_orders[0].amount, 2.0);
+    }
+
+    #[test]
+    fn test_market_order_skips_own_orders() {
+        let mut book = OrderBook::new();
+        book.add_order_for("alice", OrderType::Sell, 5.0, 50.0).unwrap();
+        book.add_order_for("bob", OrderType::Sell, 5.0, 51.0).unwrap();
+
+        let trades = book.submit_market_for("alice", OrderType::Buy, 2.0).unwrap();
+        assert_eq!((trades.len(), trades[0].sell_id, trades[0].price), (1, 2, 51.0));
+        assert_eq!(book.sell_orders[0].amount, 5.0);
+        assert_eq!(book.sell_volume, 8.0);
+    }
+
+    #[test]
+    fn test_market_order_follows_match_policy() {
+        let mut book = OrderBook::new();
+        book.set_match_policy(MatchPolicy::PriceSize);
+        book.add_order(OrderType::Sell, 1.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 3.0, 50.0).unwrap();
+
+        let trades = book.submit_market(OrderType::Buy, 1.0).unwrap();
+        assert_eq!(trades[0].sell_id, 2);
+        assert_eq!(book.sell_orders.len(), 2);
+    }
+
+    #[test]
+    fn test_market_buy_not_fully_filled() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 5.0, 50.0).unwrap();
+
+        let trades = book.submit_market(OrderType::Buy, 8.0).unwrap();
+        let filled: f64 = trades.iter().map(|t| t.amount).sum();
+        assert_eq!(filled, 5.0);
+        assert!(book.sell_orders.is_empty());
+        assert!(book.buy_orders.is_empty());
+    }
+
+    #[test]
+    fn test_fok_fully_fillable() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Sell, 5.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 51.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 53.0).unwrap();
+
+        let trades = book.submit_fok(OrderType::Buy, 8.0, 51.0).unwrap();
+        assert_eq!(trades, vec![
+            Trade { buy_id: 4, sell_id: 1, amount: 5.0, price: 50.0, maker_fee: 0.0, taker_fee: 0.0 },
+            Trade { buy_id: 4, sell_id: 2, amount: 3.0, price: 51.0, maker_fee: 0.0, taker_fee: 0.0 },
+        ]);
+        assert_eq!(book.sell_orders.len(), 2);
+        assert_eq!(book.sell_orders[0].amount, 2.0);
+    }
+
+    #[test]
+    fn test_fok_off_tick_or_lot_is_rejected() {
+        let mut book = OrderBook::new();
+        book.set_tick_size(0.05).unwrap();
+        book.set_min_lot(1.0).unwrap();
+        book.add_order(OrderType::Sell, 5.0, 50.0).unwrap();
+
+        assert_eq!(book.submit_fok(OrderType::Buy, 2.0, 50.02), Err(OrderError::InvalidTick));
+        assert_eq!(book.submit_fok(OrderType::Buy, 2.5, 50.0), Err(OrderError::InvalidLot));
+        assert_eq!(book.sell_orders[0].amount, 5.0);
+        assert!(book.trade_log().is_empty());
+    }
+
+    #[test]
+    fn test_fok_partially_fillable_is_rejected() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 5.0, 50.0).unwrap();
+        book.add_order(OrderType::Buy, 5.0, 48.0).unwrap();
+
+        let result = book.submit_fok(OrderType::Sell, 8.0, 49.0);
+        assert_eq!(result, Err(OrderError::InsufficientLiquidity));
+        assert_eq!(book.buy_orders.len(), 2);
+        assert_eq!(book.buy_orders[0].amount, 5.0);
+        assert_eq!(book.buy_orders[1].amount, 5.0);
+        assert_eq!(book.next_id, 3);
+    }
+
+    #[test]
+    fn test_uncross_known_clearing_price() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 52.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 51.0).unwrap();
+        book.add_order(OrderType::Buy, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 50.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 53.0).unwrap();
+
+        // 20 units execute at both 50 and 51, so the midpoint wins
+        assert_eq!(book.uncross(), Some((50.5, 20.0)));
+        assert_eq!(book.total_orders(), 6);
+    }
+
+    #[test]
+    fn test_uncross_single_best_price() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 51.0).unwrap();
+        book.add_order(OrderType::Sell, 4.0, 51.0).unwrap();
+        book.add_order(OrderType::Sell, 4.0, 52.0).unwrap();
+
+        assert_eq!(book.uncross(), Some((51.0, 4.0)));
+    }
+
+    #[test]
+    fn test_uncross_no_cross() {
+        let mut book = OrderBook::new();
+        assert_eq!(book.uncross(), None);
+        book.add_order(OrderType::Buy, 10.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 50.0).unwrap();
+        assert_eq!(book.uncross(), None);
+    }
+
+    #[test]
+    fn test_match_skips_self_trade() {
+        let mut book = OrderBook::new();
+        book.add_order_for("alice", OrderType::Sell, 10.0, 50.0).unwrap();
+        book.add_order_for("bob", OrderType::Sell, 10.0, 50.5).unwrap();
+        book.add_order_for("alice", OrderType::Buy, 10.0, 51.0).unwrap();
+
+        let trades = book.match_orders();
+        assert_eq!(trades, vec![Trade { buy_id: 3, sell_id: 2, amount: 10.0, price: 50.5, maker_fee: 0.0, taker_fee: 0.0 }]);
+        assert_eq!(book.sell_orders.len(), 1);
+        assert_eq!(book.sell_orders[0].owner, "alice");
+        assert!(book.buy_orders.is_empty());
+    }
+
+    #[test]
+    fn test_match_only_self_cross_leaves_book() {
+        let mut book = OrderBook::new();
+        book.add_order_for("alice", OrderType::Sell, 10.0, 50.0).unwrap();
+        book.add_order_for("alice", OrderType::Buy, 10.0, 51.0).unwrap();
+
+        assert!(book.match_orders().is_empty());
+        assert_eq!(book.total_orders(), 2);
+    }
+
+    #[test]
+    fn test_match_different_owners_cross() {
+        let mut book = OrderBook::new();
+        book.add_order_for("alice", OrderType::Sell, 10.0, 50.0).unwrap();
+        book.add_order_for("bob", OrderType::Buy, 10.0, 51.0).unwrap();
+
+        let trades = book.match_orders();
+        assert_eq!(trades, vec![Trade { buy_id: 2, sell_id: 1, amount: 10.0, price: 50.0, maker_fee: 0.0, taker_fee: 0.0 }]);
+        assert_eq!(book.total_orders(), 0);
+    }
+
+    #[test]
+    fn test_match_no_cross() {
+        let mut book = OrderBook::new();
+        book.add_order(OrderType::Buy, 10.0, 49.0).unwrap();
+        book.add_order(OrderType::Sell, 10.0, 50.0).unwrap();
+
+        assert!(book.match_orders().is_empty());
+        assert_eq!(book.total_orders(), 2);
     }
 }
\ No newline at end of file
@@ -23,20 +23,186 @@ fn main() {
 
     println!("--- Exercise 4: Manual Iteration via Destructuring ---");
     let scores = (100, 90, 80);
-    let (a, b, c) = scores;
-    let arr = [a, b, c]; // Convert to array
-    for val in arr.iter() {
+    let values = scores.to_vec(); // Same-typed tuple -> Vec via the ToVec trait
+    for val in values.iter() {
         println!("Score: {}", val);
     }
-    println!();
+    println!("Sum: {}, Average: {:.2}\n", sum_scores(scores), average_scores(scores));
 
     println!("--- Exercise 5: Tuple of Mixed Types ---");
-    let mixed = ("Rust", true, 3.14);
+    let mixed = ("Rust", true, 1.85);
     println!("Language: {}", mixed.0);
     println!("IsAwesome: {}", mixed.1);
     println!("Version: {}\n", mixed.2);
+
+    println!("--- Exercise 6: Generic Tuple Helpers ---");
+    print_pair(&point);
+    print_pair(&user);
+    let swapped = swap_pair(user);
+    print_pair(&swapped);
+    println!();
+
+    println!("--- Exercise 7: Zipping Vectors into Tuples ---");
+    let names = vec!["Alice", "Bob", "Carol"];
+    let ages = vec![30, 25, 41];
+    let heights = vec![5.6, 5.9];
+    let people = zip3(names, ages, heights);
+    for (name, age, height) in &people {
+        println!("{} is {} years old and {} tall", name, age, height);
+    }
+    let (names, ages, heights) = unzip3(people);
+    println!("Unzipped: {:?} / {:?} / {:?}\n", names, ages, heights);
+
+    println!("--- Exercise 8: Tuple <-> Struct Conversion ---");
+    let alice = Person::from(person);
+    println!("Struct: {} is {} years old and {} tall", alice.name, alice.age, alice.height);
+    let (name, age, height): (String, i32, f64) = alice.into();
+    println!("Back to a tuple: ({}, {}, {})", name, age, height);
+    println!("From get_person: {:?}\n", get_person());
 }
 
 fn get_user() -> (&'static str, i32) {
     ("Ramesh", 999)
 }
+
+// Named-field version of the Exercise 1 person tuple
+#[derive(Debug, Clone, PartialEq)]
+struct Person {
+    name: String,
+    age: i32,
+    height: f64,
+}
+
+impl From<(&str, i32, f64)> for Person {
+    fn from((name, age, height): (&str, i32, f64)) -> Person {
+        Person { name: name.to_string(), age, height }
+    }
+}
+
+// Implementing From here gives Person an Into<(String, i32, f64)> for free
+impl From<Person> for (String, i32, f64) {
+    fn from(person: Person) -> (String, i32, f64) {
+        (person.name, person.age, person.height)
+    }
+}
+
+// Same record as Exercise 1, returned as a struct instead of a tuple
+fn get_person() -> Person {
+    ("Alice", 30, 5.6).into()
+}
+
+// Works for any pair whose elements can be displayed, whatever their types
+fn print_pair<A: std::fmt::Display, B: std::fmt::Display>(t: &(A, B)) {
+    println!("({}, {})", t.0, t.1);
+}
+
+// Generic over both element types - the result has them in the opposite order
+fn swap_pair<A, B>(t: (A, B)) -> (B, A) {
+    let (a, b) = t;
+    (b, a)
+}
+
+// Tuples aren't iterable, but a tuple whose elements all share one type
+// can be turned into a Vec, which is.
+trait ToVec<T> {
+    fn to_vec(self) -> Vec<T>;
+}
+
+impl<T> ToVec<T> for (T, T) {
+    fn to_vec(self) -> Vec<T> {
+        vec![self.0, self.1]
+    }
+}
+
+impl<T> ToVec<T> for (T, T, T) {
+    fn to_vec(self) -> Vec<T> {
+        vec![self.0, self.1, self.2]
+    }
+}
+
+impl<T> ToVec<T> for (T, T, T, T) {
+    fn to_vec(self) -> Vec<T> {
+        vec![self.0, self.1, self.2, self.3]
+    }
+}
+
+// The tuple type fixes the arity at three, so no length check is needed
+fn sum_scores(scores: (i32, i32, i32)) -> i32 {
+    let (a, b, c) = scores;
+    a + b + c
+}
+
+fn average_scores(scores: (i32, i32, i32)) -> f64 {
+    sum_scores(scores) as f64 / 3.0
+}
+
+// Pair up three vectors element-wise, stopping at the shortest one
+fn zip3<A, B, C>(a: Vec<A>, b: Vec<B>, c: Vec<C>) -> Vec<(A, B, C)> {
+    a.into_iter()
+        .zip(b)
+        .zip(c)
+        .map(|((a, b), c)| (a, b, c))
+        .collect()
+}
+
+// Inverse of zip3 - split a vector of triples back into three vectors
+fn unzip3<A, B, C>(v: Vec<(A, B, C)>) -> (Vec<A>, Vec<B>, Vec<C>) {
+    let mut a = Vec::with_capacity(v.len());
+    let mut b = Vec::with_capacity(v.len());
+    let mut c = Vec::with_capacity(v.len());
+    for (x, y, z) in v {
+        a.push(x);
+        b.push(y);
+        c.push(z);
+    }
+    (a, b, c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_pair() {
+        assert_eq!(swap_pair((1, "x")), ("x", 1));
+        assert_eq!(swap_pair(swap_pair((2.5, 'c'))), (2.5, 'c'));
+    }
+
+    #[test]
+    fn test_to_vec() {
+        assert_eq!((100, 90, 80).to_vec(), vec![100, 90, 80]);
+        assert_eq!(("a", "b").to_vec(), vec!["a", "b"]);
+        assert_eq!((1.0, 2.0, 3.0, 4.0).to_vec(), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_person_round_trip() {
+        let person = Person::from(("Alice", 30, 5.6));
+        assert_eq!(person, Person { name: "Alice".to_string(), age: 30, height: 5.6 });
+        let tuple: (String, i32, f64) = person.into();
+        assert_eq!(tuple, ("Alice".to_string(), 30, 5.6));
+        assert_eq!(get_person().name, "Alice");
+    }
+
+    #[test]
+    fn test_sum_and_average_scores() {
+        assert_eq!(sum_scores((100, 90, 80)), 270);
+        assert_eq!(average_scores((100, 90, 80)), 90.0);
+        assert_eq!(average_scores((1, 2, 2)), 5.0 / 3.0);
+    }
+
+    #[test]
+    fn test_zip3_equal_lengths() {
+        let zipped = zip3(vec![1, 2], vec!["a", "b"], vec![true, false]);
+        assert_eq!(zipped, vec![(1, "a", true), (2, "b", false)]);
+        assert_eq!(unzip3(zipped), (vec![1, 2], vec!["a", "b"], vec![true, false]));
+    }
+
+    #[test]
+    fn test_zip3_truncates_to_shortest() {
+        let zipped = zip3(vec![1, 2, 3], vec!["a"], vec![0.5, 1.5]);
+        assert_eq!(zipped, vec![(1, "a", 0.5)]);
+        let empty: Vec<(i32, i32, i32)> = zip3(vec![], vec![1], vec![2]);
+        assert!(empty.is_empty());
+    }
+}